@@ -0,0 +1,150 @@
+use bevy::prelude::Entity;
+use bevy_ecs_ldtk::GridCoords;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use the_haunted_mansion::navigation::{
+    NavChunk, NavGrid, RoomLinks, WalkableState, DOORWAY_COST, FLOOR_COST, FLOOR_ROOM_SPACING,
+    ROOM_TILE_COUNT,
+};
+
+/// A room with walls all around and a doorway in the middle of every side which has a door
+fn room_chunk(links: RoomLinks) -> NavChunk {
+    let mut chunk = NavChunk::new(links);
+    let last = ROOM_TILE_COUNT - 1;
+    let middle = ROOM_TILE_COUNT / 2;
+
+    for x in 0..ROOM_TILE_COUNT {
+        for y in 0..ROOM_TILE_COUNT {
+            let on_edge = x == 0 || y == 0 || x == last || y == last;
+            let in_doorway = (x == middle || x == middle - 1 || y == middle || y == middle - 1)
+                && match (x, y) {
+                    (_, y) if y == last => links.doors & 0b1000 != 0,
+                    (x, _) if x == last => links.doors & 0b0100 != 0,
+                    (_, 0) => links.doors & 0b0010 != 0,
+                    (0, _) => links.doors & 0b0001 != 0,
+                    _ => false,
+                };
+
+            let state = match (on_edge, in_doorway) {
+                (false, _) => WalkableState::Walkable(FLOOR_COST),
+                (true, true) => WalkableState::Walkable(DOORWAY_COST),
+                (true, false) => WalkableState::NotWalkable,
+            };
+
+            chunk.set(GridCoords::new(x, y), Some(state));
+        }
+    }
+
+    chunk
+}
+
+/// A square of `side` by `side` rooms on each floor, every room opening into its neighbours and
+/// the first room of each floor holding the stairs to the next one up
+fn mansion(side: i32, floors: i8) -> NavGrid {
+    let mut grid = NavGrid::default();
+    let mut rooms = 0;
+
+    for floor in 0..floors {
+        for x in 0..side {
+            for y in 0..side {
+                let doors = (if y < side - 1 { 0b1000 } else { 0 })
+                    | (if x < side - 1 { 0b0100 } else { 0 })
+                    | (if y > 0 { 0b0010 } else { 0 })
+                    | (if x > 0 { 0b0001 } else { 0 });
+                let stairs_to = (x == 0 && y == 0).then(|| {
+                    if floor + 1 < floors {
+                        floor + 1
+                    } else {
+                        floor - 1
+                    }
+                });
+
+                let links = RoomLinks {
+                    doors,
+                    floor,
+                    stairs_to: stairs_to.filter(|_| floors > 1),
+                };
+
+                grid.insert_chunk(
+                    Entity::from_raw(rooms),
+                    GridCoords::new(x, y + floor as i32 * FLOOR_ROOM_SPACING),
+                    room_chunk(links),
+                );
+                rooms += 1;
+            }
+        }
+    }
+
+    grid
+}
+
+/// The middle of a room, in world tile coordinates
+fn room_center(x: i32, y: i32) -> GridCoords {
+    NavGrid::room_origin(GridCoords::new(x, y))
+        + GridCoords::new(ROOM_TILE_COUNT / 2, ROOM_TILE_COUNT / 2)
+}
+
+fn across_one_floor(c: &mut Criterion) {
+    let mut group = c.benchmark_group("across_one_floor");
+
+    for side in [2, 4, 7] {
+        let grid = mansion(side, 1);
+        let from = room_center(0, 0);
+        let to = room_center(side - 1, side - 1);
+        let rooms = side * side;
+
+        group.bench_with_input(BenchmarkId::new("tiles", rooms), &grid, |b, grid| {
+            b.iter(|| grid.find_tile_path(from, to, |_| true))
+        });
+        group.bench_with_input(
+            BenchmarkId::new("rooms_then_tiles", rooms),
+            &grid,
+            |b, grid| b.iter(|| grid.find_path(from, to)),
+        );
+    }
+
+    group.finish();
+}
+
+fn across_floors(c: &mut Criterion) {
+    let mut group = c.benchmark_group("across_floors");
+
+    // Three floors of 16 rooms, from the far corner of the ground floor to the top floor
+    let grid = mansion(4, 3);
+    let from = room_center(3, 3);
+    let to = room_center(3, 3 + 2 * FLOOR_ROOM_SPACING);
+
+    group.bench_function("rooms_then_tiles", |b| b.iter(|| grid.find_path(from, to)));
+    group.finish();
+}
+
+fn reachable(c: &mut Criterion) {
+    let grid = mansion(7, 1);
+    let from = room_center(3, 3);
+
+    c.bench_function("reachable_within_8_speed", |b| {
+        b.iter(|| grid.reachable(from, 8 * ROOM_TILE_COUNT as u32))
+    });
+}
+
+fn diagonal_and_smoothed(c: &mut Criterion) {
+    let mut group = c.benchmark_group("diagonal_and_smoothed");
+
+    let mut grid = mansion(7, 1);
+    grid.set_diagonals(true);
+    let from = room_center(0, 0);
+    let to = room_center(6, 6);
+    let path = grid.find_path(from, to).unwrap();
+
+    group.bench_function("rooms_then_tiles", |b| b.iter(|| grid.find_path(from, to)));
+    group.bench_function("smooth_path", |b| b.iter(|| grid.smooth_path(&path)));
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    across_one_floor,
+    across_floors,
+    reachable,
+    diagonal_and_smoothed
+);
+criterion_main!(benches);
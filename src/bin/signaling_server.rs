@@ -0,0 +1,36 @@
+//! Runs the bundled signaling server on its own, for games on a LAN or offline testing.
+//!
+//! `cargo run --bin signaling_server -- --port 3536`, then start the game with
+//! `--server ws://<this machine>:3536`.
+use bevy::{app::ScheduleRunnerPlugin, log::LogPlugin, prelude::*};
+use std::time::Duration;
+use the_haunted_mansion::signaling::{start_signaling_server, SIGNALING_PORT};
+
+fn main() {
+    let port = port_from_args().unwrap_or(SIGNALING_PORT);
+
+    App::new()
+        .add_plugins((
+            MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(Duration::from_secs_f64(
+                1. / 60.,
+            ))),
+            LogPlugin::default(),
+        ))
+        // Started once logging is up, so the server's own messages are shown
+        .add_systems(Startup, move |mut commands: Commands| {
+            commands.insert_resource(start_signaling_server(port));
+        })
+        .run();
+}
+
+/// The value of `--port <port>` or `--port=<port>`
+fn port_from_args() -> Option<u16> {
+    let args = std::env::args().collect::<Vec<_>>();
+
+    args.iter()
+        .enumerate()
+        .find_map(|(index, arg)| match arg.as_str() {
+            "--port" => args.get(index + 1)?.parse().ok(),
+            _ => arg.strip_prefix("--port=")?.parse().ok(),
+        })
+}
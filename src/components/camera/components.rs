@@ -4,4 +4,4 @@ use bevy::prelude::*;
 pub struct PlayerCamera;
 
 #[derive(Component)]
-pub struct CameraAnchor;
\ No newline at end of file
+pub struct CameraAnchor;
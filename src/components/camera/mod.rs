@@ -20,6 +20,7 @@ impl Plugin for CameraPlugin {
             .add_systems(
                 Update,
                 update_mouse_coords.run_if(in_state(GameState::Main)),
-            );
+            )
+            .add_systems(Update, show_focused_floor.run_if(in_state(GameState::Main)));
     }
 }
@@ -1,6 +1,16 @@
 use super::{components::*, CAMERA_MOVE_SPEED};
-use crate::{components::{character::CharacterInput, MouseToWorldCoords, Selectable}, ui::OccludeUI};
-use bevy::{core_pipeline::clear_color::ClearColorConfig, prelude::*, render::primitives::Aabb, sprite::collide_aabb::collide, window::PrimaryWindow};
+use crate::{
+    components::{
+        character::{CharacterInput, CharacterProps},
+        room::RoomLevel,
+        MouseToWorldCoords, Room, Selectable,
+    },
+    ui::OccludeUI,
+};
+use bevy::{
+    core_pipeline::clear_color::ClearColorConfig, prelude::*, render::primitives::Aabb,
+    sprite::collide_aabb::collide, window::PrimaryWindow,
+};
 use leafwing_input_manager::action_state::ActionState;
 
 pub fn spawn_camera(mut commands: Commands) {
@@ -132,3 +142,45 @@ pub fn update_mouse_coords(
         }
     }
 }
+
+/// Only show the floor that the camera is currently looking at
+pub fn show_focused_floor(
+    anchor: Query<&GlobalTransform, With<CameraAnchor>>,
+    mut rooms: Query<(&RoomLevel, &mut Visibility), With<Room>>,
+    mut characters: Query<
+        (&GlobalTransform, &mut Visibility),
+        (With<CharacterProps>, Without<Room>),
+    >,
+) {
+    let Ok(anchor_transform) = anchor.get_single() else {
+        return;
+    };
+
+    let focused_level = RoomLevel::from_world_position(anchor_transform.translation().truncate());
+
+    for (level, mut visibility) in &mut rooms {
+        let next_visibility = if *level == focused_level {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+
+        if *visibility != next_visibility {
+            *visibility = next_visibility;
+        }
+    }
+
+    for (transform, mut visibility) in &mut characters {
+        let next_visibility = if RoomLevel::from_world_position(transform.translation().truncate())
+            == focused_level
+        {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+
+        if *visibility != next_visibility {
+            *visibility = next_visibility;
+        }
+    }
+}
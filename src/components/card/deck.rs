@@ -0,0 +1,144 @@
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// A deck of cards with a draw pile, a discard pile and the cards removed from the game.
+///
+/// Shuffling only depends on the seed and how many times the deck has been shuffled, so the
+/// deck can be serialized and sent to other peers without them ending up with a different order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Deck<T> {
+    seed: u64,
+    shuffle_count: u64,
+    draw_pile: VecDeque<T>,
+    discard_pile: Vec<T>,
+    removed: Vec<T>,
+}
+
+impl<T: Clone> Deck<T> {
+    pub fn new(cards: &[T], seed: u64) -> Self {
+        let mut deck = Self {
+            seed,
+            shuffle_count: 0,
+            draw_pile: VecDeque::new(),
+            discard_pile: cards.to_vec(),
+            removed: Vec::new(),
+        };
+
+        deck.reshuffle();
+        deck
+    }
+
+    /// Draw the top card, shuffling the discard pile back in first if the draw pile is empty.
+    /// Returns `None` when every card is in play or removed from the game
+    pub fn draw(&mut self) -> Option<T> {
+        if self.draw_pile.is_empty() {
+            self.reshuffle();
+        }
+
+        self.draw_pile.pop_front()
+    }
+
+    pub fn discard(&mut self, card: T) {
+        self.discard_pile.push(card);
+    }
+
+    /// Take a card out of the game, it will never be shuffled back into the deck
+    pub fn remove_from_game(&mut self, card: T) {
+        self.removed.push(card);
+    }
+
+    /// Shuffle the discard pile into the draw pile
+    pub fn reshuffle(&mut self) {
+        if self.discard_pile.is_empty() {
+            return;
+        }
+
+        let mut rng = StdRng::seed_from_u64(self.seed.wrapping_add(self.shuffle_count));
+        self.shuffle_count += 1;
+
+        let mut cards = self.draw_pile.drain(..).collect::<Vec<_>>();
+        cards.append(&mut self.discard_pile);
+        cards.shuffle(&mut rng);
+
+        self.draw_pile = cards.into();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cards() -> Vec<u32> {
+        (0..20).collect()
+    }
+
+    /// Draws until the deck runs out, shuffling the discard pile back in if there is one
+    fn draw_all(deck: &mut Deck<u32>) -> Vec<u32> {
+        std::iter::from_fn(|| deck.draw()).collect()
+    }
+
+    fn sorted(mut cards: Vec<u32>) -> Vec<u32> {
+        cards.sort();
+        cards
+    }
+
+    #[test]
+    fn same_seed_same_order() {
+        let mut a = Deck::new(&cards(), 7);
+        let mut b = Deck::new(&cards(), 7);
+
+        let drawn = draw_all(&mut a);
+        assert_eq!(drawn, draw_all(&mut b));
+        assert_eq!(sorted(drawn.clone()), cards());
+        assert_ne!(drawn, draw_all(&mut Deck::new(&cards(), 8)));
+
+        // Later shuffles line up too
+        for card in drawn {
+            a.discard(card);
+            b.discard(card);
+        }
+        assert_eq!(draw_all(&mut a), draw_all(&mut b));
+    }
+
+    #[test]
+    fn reshuffle_only_draws_from_the_discard_pile() {
+        let mut deck = Deck::new(&cards(), 3);
+        let drawn = draw_all(&mut deck);
+        assert!(deck.draw().is_none());
+
+        // The rest stay in hands and must not come back
+        let discarded = drawn[..5].to_vec();
+        for card in &discarded {
+            deck.discard(*card);
+        }
+
+        assert_eq!(sorted(draw_all(&mut deck)), sorted(discarded));
+        assert!(deck.discard_pile.is_empty());
+    }
+
+    #[test]
+    fn removed_cards_never_come_back() {
+        let mut deck = Deck::new(&cards(), 11);
+        let drawn = draw_all(&mut deck);
+        let (removed, kept) = drawn.split_at(8);
+
+        for card in removed {
+            deck.remove_from_game(*card);
+        }
+        for card in kept {
+            deck.discard(*card);
+        }
+
+        for _ in 0..5 {
+            let drawn = draw_all(&mut deck);
+            assert_eq!(sorted(drawn.clone()), sorted(kept.to_vec()));
+
+            for card in drawn {
+                deck.discard(card);
+            }
+        }
+
+        assert_eq!(sorted(deck.removed.clone()), sorted(removed.to_vec()));
+    }
+}
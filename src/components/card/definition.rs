@@ -0,0 +1,127 @@
+use super::{CardEffect, CardType, EventCard, ItemCard, OmenCard};
+use bevy::asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext};
+use bevy::prelude::*;
+use bevy::reflect::TypePath;
+use bevy::utils::BoxedFuture;
+use bevy_asset_loader::prelude::*;
+use serde::Deserialize;
+use std::collections::HashMap;
+use thiserror::Error;
+
+#[derive(AssetCollection, Resource)]
+pub struct CardAssets {
+    #[asset(path = "cards/haunted.cards.ron")]
+    pub definitions: Handle<CardDefinitions>,
+}
+
+/// The text, art and effects of a single card
+#[derive(Debug, Clone, Deserialize)]
+pub struct CardDefinition {
+    pub name: String,
+    #[serde(default)]
+    pub flavour: String,
+    /// Path to the card art, relative to the assets folder
+    #[serde(default)]
+    pub art: Option<String>,
+    /// Resolved when the card is drawn
+    #[serde(default)]
+    pub effects: Vec<CardEffect>,
+    /// Resolved when a carried item or omen is used from the inventory
+    #[serde(default)]
+    pub on_use: Vec<CardEffect>,
+    /// What happens to the card once it has been used
+    #[serde(default)]
+    pub after_use: AfterUse,
+}
+
+/// Where a used item or omen goes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+pub enum AfterUse {
+    /// The character keeps carrying it
+    #[default]
+    Keep,
+    /// It goes to the discard pile, to be drawn again once the deck is reshuffled
+    Discard,
+    /// It leaves the game and is never drawn again
+    RemoveFromGame,
+}
+
+/// Every card of the game, loaded from a `.cards.ron` file
+#[derive(Asset, TypePath, Debug, Deserialize)]
+pub struct CardDefinitions {
+    pub events: HashMap<EventCard, CardDefinition>,
+    pub omens: HashMap<OmenCard, CardDefinition>,
+    pub items: HashMap<ItemCard, CardDefinition>,
+}
+
+impl CardDefinitions {
+    pub fn get(&self, card: CardType) -> Option<&CardDefinition> {
+        match card {
+            CardType::Event(card) => self.events.get(&card),
+            CardType::Omen(card) => self.omens.get(&card),
+            CardType::Item(card) => self.items.get(&card),
+        }
+    }
+
+    /// Every card which is missing a definition
+    pub fn missing(&self) -> Vec<CardType> {
+        let events = EventCard::ALL
+            .iter()
+            .filter(|card| !self.events.contains_key(*card))
+            .map(|card| CardType::Event(*card));
+        let omens = OmenCard::ALL
+            .iter()
+            .filter(|card| !self.omens.contains_key(*card))
+            .map(|card| CardType::Omen(*card));
+        let items = ItemCard::ALL
+            .iter()
+            .filter(|card| !self.items.contains_key(*card))
+            .map(|card| CardType::Item(*card));
+
+        events.chain(omens).chain(items).collect()
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum CardDefinitionsError {
+    #[error("could not read the card definitions: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse the card definitions: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+    #[error("missing card definitions for {0:?}")]
+    Missing(Vec<CardType>),
+}
+
+#[derive(Default)]
+pub struct CardDefinitionsLoader;
+
+impl AssetLoader for CardDefinitionsLoader {
+    type Asset = CardDefinitions;
+    type Settings = ();
+    type Error = CardDefinitionsError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a Self::Settings,
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+
+            let definitions = ron::de::from_bytes::<CardDefinitions>(&bytes)?;
+
+            let missing = definitions.missing();
+            if !missing.is_empty() {
+                return Err(CardDefinitionsError::Missing(missing));
+            }
+
+            Ok(definitions)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["cards.ron"]
+    }
+}
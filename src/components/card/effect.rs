@@ -0,0 +1,251 @@
+use crate::components::character::CharacterTraits;
+pub use crate::components::character::Trait;
+use serde::{Deserialize, Serialize};
+
+/// A single step of resolving a card, always applied to the character who drew it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum CardEffect {
+    /// Move one of the character's traits up or down its track
+    ChangeTrait { stat: Trait, amount: i8 },
+    /// Roll as many dice as the trait's value, then resolve the highest outcome reached
+    TraitRoll {
+        stat: Trait,
+        outcomes: Vec<RollOutcome>,
+    },
+    /// Let the player pick which effects to resolve
+    Choice {
+        prompt: String,
+        options: Vec<ChoiceOption>,
+    },
+    /// Move the character to the first placed room with this name
+    MoveTo { room: String },
+    /// Draw the top card of the item deck
+    GainItem,
+    /// Give up an item, it goes to the discard pile
+    LoseItem(super::ItemCard),
+    /// Spawn a named token in the room the card was drawn in
+    Spawn { name: String },
+    /// Turn the room the card was drawn in counter-clockwise
+    RotateRoom { turns: u8 },
+    /// Make every step in the room the card was drawn in use up at least this much movement
+    SlowRoom { cost: u8 },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RollOutcome {
+    /// The lowest roll total giving this outcome
+    pub at_least: u32,
+    pub effects: Vec<CardEffect>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChoiceOption {
+    pub label: String,
+    pub effects: Vec<CardEffect>,
+}
+
+/// What is left to do after resolving an effect against the character's traits
+#[derive(Debug, Clone, PartialEq)]
+pub enum EffectStep {
+    Done,
+    /// These effects have to be resolved before anything else queued
+    Then(Vec<CardEffect>),
+    /// Resolution stops until the player picks one of the options
+    Prompt {
+        prompt: String,
+        options: Vec<ChoiceOption>,
+    },
+    /// The effect needs access to the rest of the game, like rooms or decks
+    World(CardEffect),
+}
+
+impl CardEffect {
+    /// Resolve the parts of an effect which only touch the character's traits.
+    ///
+    /// `roll` makes a trait roll for the character and returns the total, so resolution does not
+    /// depend on the game running and can be driven with fixed rolls.
+    pub fn resolve(
+        self,
+        traits: &mut CharacterTraits,
+        haunt_started: bool,
+        roll: impl FnOnce(Trait, &CharacterTraits) -> u32,
+    ) -> EffectStep {
+        match self {
+            CardEffect::ChangeTrait { stat, amount } => {
+                traits.track_mut(stat).shift(amount, haunt_started);
+                EffectStep::Done
+            }
+            CardEffect::TraitRoll { stat, outcomes } => {
+                let total = roll(stat, traits);
+
+                outcomes
+                    .into_iter()
+                    .filter(|outcome| outcome.at_least <= total)
+                    .max_by_key(|outcome| outcome.at_least)
+                    .map(|outcome| EffectStep::Then(outcome.effects))
+                    .unwrap_or(EffectStep::Done)
+            }
+            CardEffect::Choice { prompt, options } => EffectStep::Prompt { prompt, options },
+            effect => EffectStep::World(effect),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::character::TraitTrack;
+
+    fn average_traits() -> CharacterTraits {
+        let track = TraitTrack::new([1, 2, 3, 4, 5, 6, 7, 8], 3);
+
+        CharacterTraits {
+            speed: track,
+            might: track,
+            sanity: track,
+            knowledge: track,
+        }
+    }
+
+    fn no_roll(_: Trait, _: &CharacterTraits) -> u32 {
+        panic!("Nothing should be rolled")
+    }
+
+    /// Outcomes listed out of order, to check the highest one reached wins rather than the last
+    fn sanity_roll() -> CardEffect {
+        CardEffect::TraitRoll {
+            stat: Trait::Sanity,
+            outcomes: vec![
+                RollOutcome {
+                    at_least: 0,
+                    effects: vec![CardEffect::ChangeTrait {
+                        stat: Trait::Sanity,
+                        amount: -1,
+                    }],
+                },
+                RollOutcome {
+                    at_least: 4,
+                    effects: vec![CardEffect::GainItem],
+                },
+                RollOutcome {
+                    at_least: 2,
+                    effects: vec![CardEffect::ChangeTrait {
+                        stat: Trait::Sanity,
+                        amount: 1,
+                    }],
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn change_trait_moves_only_that_trait() {
+        let mut traits = average_traits();
+
+        let step = CardEffect::ChangeTrait {
+            stat: Trait::Might,
+            amount: 2,
+        }
+        .resolve(&mut traits, false, no_roll);
+
+        assert_eq!(step, EffectStep::Done);
+        assert_eq!(traits.value(Trait::Might), 6);
+        assert_eq!(traits.value(Trait::Speed), 4);
+        assert_eq!(traits.value(Trait::Sanity), 4);
+        assert_eq!(traits.value(Trait::Knowledge), 4);
+    }
+
+    #[test]
+    fn change_trait_can_kill_only_after_the_haunt() {
+        let lose_all = CardEffect::ChangeTrait {
+            stat: Trait::Speed,
+            amount: -8,
+        };
+
+        let mut traits = average_traits();
+        lose_all.clone().resolve(&mut traits, false, no_roll);
+        assert!(!traits.fell_off());
+
+        let mut traits = average_traits();
+        lose_all.resolve(&mut traits, true, no_roll);
+        assert!(traits.fell_off());
+    }
+
+    #[test]
+    fn trait_roll_resolves_the_highest_outcome_reached() {
+        let lose_sanity = vec![CardEffect::ChangeTrait {
+            stat: Trait::Sanity,
+            amount: -1,
+        }];
+        let gain_sanity = vec![CardEffect::ChangeTrait {
+            stat: Trait::Sanity,
+            amount: 1,
+        }];
+
+        for (total, effects) in [
+            (0, lose_sanity.clone()),
+            (1, lose_sanity),
+            (2, gain_sanity.clone()),
+            (3, gain_sanity),
+            (4, vec![CardEffect::GainItem]),
+            (12, vec![CardEffect::GainItem]),
+        ] {
+            let step = sanity_roll().resolve(&mut average_traits(), false, |stat, _| {
+                assert_eq!(stat, Trait::Sanity);
+                total
+            });
+
+            assert_eq!(step, EffectStep::Then(effects), "rolled {}", total);
+        }
+    }
+
+    #[test]
+    fn trait_roll_below_every_outcome_does_nothing() {
+        let effect = CardEffect::TraitRoll {
+            stat: Trait::Knowledge,
+            outcomes: vec![RollOutcome {
+                at_least: 5,
+                effects: vec![CardEffect::GainItem],
+            }],
+        };
+
+        let mut traits = average_traits();
+        let step = effect.resolve(&mut traits, false, |_, _| 4);
+
+        assert_eq!(step, EffectStep::Done);
+        assert_eq!(traits, average_traits());
+    }
+
+    #[test]
+    fn choice_prompts_with_every_option() {
+        let options = vec![
+            ChoiceOption {
+                label: "Run".to_string(),
+                effects: vec![CardEffect::ChangeTrait {
+                    stat: Trait::Speed,
+                    amount: 1,
+                }],
+            },
+            ChoiceOption {
+                label: "Hide".to_string(),
+                effects: vec![CardEffect::SlowRoom { cost: 2 }],
+            },
+        ];
+
+        let mut traits = average_traits();
+        let step = CardEffect::Choice {
+            prompt: "Something is coming".to_string(),
+            options: options.clone(),
+        }
+        .resolve(&mut traits, false, no_roll);
+
+        assert_eq!(
+            step,
+            EffectStep::Prompt {
+                prompt: "Something is coming".to_string(),
+                options,
+            }
+        );
+        assert_eq!(traits, average_traits());
+    }
+}
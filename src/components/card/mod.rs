@@ -0,0 +1,303 @@
+mod deck;
+mod definition;
+mod effect;
+mod resolution;
+
+use crate::components::{shared_seed, NetworkedLobby, RandomStream};
+use crate::events::GameEvent;
+use crate::GameState;
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+use bevy_asset_loader::prelude::*;
+pub use deck::Deck;
+pub use definition::*;
+pub use effect::*;
+use rand::prelude::*;
+pub use resolution::*;
+use serde::{Deserialize, Serialize};
+
+/// Declares a card enum along with `ALL`, the list of its variants used to build its deck
+macro_rules! cards {
+    ($name:ident { $($card:ident),* $(,)? }) => {
+        #[derive(Reflect, Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+        pub enum $name {
+            $($card),*
+        }
+
+        impl $name {
+            pub const ALL: &'static [Self] = &[$(Self::$card),*];
+        }
+    };
+}
+
+pub struct CardPlugin;
+
+impl Plugin for CardPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<CardDefinitions>()
+            .init_asset_loader::<CardDefinitionsLoader>()
+            .add_collection_to_loading_state::<_, CardAssets>(GameState::Loading)
+            .register_type::<CardType>()
+            .init_resource::<CardResolution>()
+            .add_event::<CardDrawnEvent>()
+            .add_event::<UseCardEvent>()
+            .add_event::<CardPromptEvent>()
+            .add_event::<CardChoiceEvent>()
+            .add_event::<CardEffectEvent>()
+            .add_systems(OnEnter(GameState::InitialSpawn), shuffle_card_decks)
+            .add_systems(
+                Update,
+                (
+                    draw_room_card,
+                    queue_drawn_card,
+                    use_inventory_card,
+                    choose_with_number_keys,
+                    answer_card_prompt,
+                    resolve_card_effects,
+                    show_card_prompt,
+                    apply_card_effects,
+                )
+                    .chain()
+                    .run_if(resource_exists::<CardDecks>()),
+            );
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+pub enum CardType {
+    Event(EventCard),
+    Omen(OmenCard),
+    Item(ItemCard),
+}
+
+/// The symbol printed on a room tile, telling which deck to draw from when entering it
+#[derive(Reflect, Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CardSymbol {
+    Event,
+    Omen,
+    Item,
+}
+
+impl CardSymbol {
+    /// Parse the `CardSymbol` enum used in the LDtk project
+    pub fn from_ldtk(value: &str) -> Option<Self> {
+        match value {
+            "Event" => Some(Self::Event),
+            "Omen" => Some(Self::Omen),
+            "Item" => Some(Self::Item),
+            _ => None,
+        }
+    }
+}
+
+cards! {
+    ItemCard {
+        RabbitsFoot,
+        MedicalKit,
+        Armor,
+        Axe,
+        PickpocketsGloves,
+        DarkDice,
+        AngelFeather,
+        BloodDagger,
+        Revolver,
+        AmuletOfTheAges,
+        AdrenalineShot,
+        SmellingSalts,
+        Bell,
+        Candle,
+        Bottle,
+        PuzzleBox,
+        SacrificialDagger,
+        Dynamite,
+        HealingSalve,
+        Idol,
+        LuckyStone,
+    }
+}
+
+cards! {
+    EventCard {
+        TheBeckoning,
+        Groundskeeper,
+        TheWalls,
+        LockedSafe,
+        GraveDirt,
+        Skeletons,
+        TheVoice,
+        ClosetDoor,
+        Rotten,
+        Footsteps,
+        Smoke,
+        SecretPassage,
+        Whoops,
+        MysticSlide,
+        MistsFromTheWalls,
+        Spider,
+        JonahsTurn,
+        Silence,
+        HangedMen,
+        Debris,
+        Funeral,
+        SecretStairs,
+        WhatThe,
+        AngryBeing,
+        AMomentOfHope,
+        Webs,
+        DisquietingSounds,
+        HideousShriek,
+        RevolvingWall,
+        CreepyCrawlies,
+        BurningMan,
+        TheLostOne,
+        SomethingHidden,
+        BloodyVision,
+        CreepyPuppet,
+        ImageInTheMirror1,
+        Possession,
+        ShriekingWind,
+        PhoneCall,
+        LightsOut,
+        DripDripDrip,
+        ItIsMeantToBe,
+        SomethingSlimy,
+        ImageInTheMirror2,
+        NightView,
+    }
+}
+
+cards! {
+    OmenCard {
+        Girl,
+        SpiritBoard,
+        Dog,
+        Book,
+        Madman,
+        Medallion,
+        HolySymbol,
+        Ring,
+        Skull,
+        CrystalBall,
+        Bite,
+        Mask,
+        Spear,
+    }
+}
+
+/// The Event, Omen and Item decks drawn from when entering a room with a card symbol
+#[derive(Resource, Debug, Clone, Serialize, Deserialize)]
+pub struct CardDecks {
+    pub events: Deck<EventCard>,
+    pub omens: Deck<OmenCard>,
+    pub items: Deck<ItemCard>,
+}
+
+impl CardDecks {
+    pub fn new(seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        Self {
+            events: Deck::new(EventCard::ALL, rng.gen()),
+            omens: Deck::new(OmenCard::ALL, rng.gen()),
+            items: Deck::new(ItemCard::ALL, rng.gen()),
+        }
+    }
+
+    /// Draw the top card of the deck matching the symbol, `None` once that deck has run out
+    pub fn draw(&mut self, symbol: CardSymbol) -> Option<CardType> {
+        match symbol {
+            CardSymbol::Event => self.events.draw().map(CardType::Event),
+            CardSymbol::Omen => self.omens.draw().map(CardType::Omen),
+            CardSymbol::Item => self.items.draw().map(CardType::Item),
+        }
+    }
+
+    pub fn discard(&mut self, card: CardType) {
+        match card {
+            CardType::Event(card) => self.events.discard(card),
+            CardType::Omen(card) => self.omens.discard(card),
+            CardType::Item(card) => self.items.discard(card),
+        }
+    }
+
+    /// Take a card out of the game for good, it is never shuffled back in
+    pub fn remove_from_game(&mut self, card: CardType) {
+        match card {
+            CardType::Event(card) => self.events.remove_from_game(card),
+            CardType::Omen(card) => self.omens.remove_from_game(card),
+            CardType::Item(card) => self.items.remove_from_game(card),
+        }
+    }
+}
+
+/// Marks a room whose card has already been drawn, so entering it again does not draw another
+#[derive(Component)]
+pub struct RoomCardDrawn;
+
+/// Use the item or omen in one of the character's inventory slots
+#[derive(Event, Debug)]
+pub struct UseCardEvent {
+    pub character_entity: Entity,
+    pub slot: usize,
+}
+
+#[derive(Event, Debug)]
+pub struct CardDrawnEvent {
+    pub character_entity: Entity,
+    pub room_entity: Entity,
+    pub card: CardType,
+}
+
+fn shuffle_card_decks(mut commands: Commands, lobby: Option<Res<NetworkedLobby>>) {
+    let seed = shared_seed(lobby.as_deref(), RandomStream::CardDecks);
+    info!("Shuffling the card decks with seed {}", seed);
+
+    commands.insert_resource(CardDecks::new(seed));
+}
+
+fn draw_room_card(
+    mut commands: Commands,
+    mut decks: ResMut<CardDecks>,
+    mut game_event: EventReader<GameEvent>,
+    drawn_rooms: Query<(), With<RoomCardDrawn>>,
+    mut card_drawn_event: EventWriter<CardDrawnEvent>,
+) {
+    // The marker is only inserted once commands are applied, so also track the rooms drawn in
+    // this run in case two characters entered the same room this frame
+    let mut drawn_this_run = HashSet::new();
+
+    for event in &mut game_event.read() {
+        let GameEvent::RoomEnter {
+            character_entity,
+            room_entity,
+            room,
+        } = event
+        else {
+            continue;
+        };
+
+        let Some(symbol) = room.card else {
+            continue;
+        };
+
+        if drawn_rooms.contains(*room_entity) || !drawn_this_run.insert(*room_entity) {
+            continue;
+        }
+
+        commands.entity(*room_entity).insert(RoomCardDrawn);
+
+        let Some(card) = decks.draw(symbol) else {
+            warn!(
+                "The {:?} deck is empty, no card drawn in {}",
+                symbol, room.name
+            );
+            continue;
+        };
+
+        card_drawn_event.send(CardDrawnEvent {
+            character_entity: *character_entity,
+            room_entity: *room_entity,
+            card,
+        });
+    }
+}
@@ -0,0 +1,454 @@
+use super::effect::{CardEffect, ChoiceOption, EffectStep};
+use super::{
+    AfterUse, CardAssets, CardDecks, CardDefinitions, CardDrawnEvent, CardType, UseCardEvent,
+};
+use crate::components::character::{
+    CharacterProps, CharacterTraits, CurrentRoom, Inventory, Player,
+};
+use crate::components::dice::{DiceRng, DiceRolledEvent, RollModifiers};
+use crate::components::navmesh::MeshGrid;
+use crate::components::room::{
+    room_location_to_position, CharacterTeleportEvent, Room, RoomLevel, RotateRoomEvent, ROOM_SIZE,
+};
+use crate::components::{Haunt, TurnState};
+use bevy::prelude::*;
+use bevy_ecs_ldtk::GridCoords;
+use std::collections::VecDeque;
+
+const CHOICE_KEYS: [KeyCode; 9] = [
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+];
+
+#[derive(Debug, Clone)]
+pub struct PendingEffect {
+    pub character_entity: Entity,
+    pub room_entity: Entity,
+    pub effect: CardEffect,
+}
+
+#[derive(Debug, Clone)]
+pub struct PendingChoice {
+    pub character_entity: Entity,
+    pub room_entity: Entity,
+    pub options: Vec<ChoiceOption>,
+}
+
+/// The effects of drawn cards which are still waiting to be resolved, in order
+#[derive(Resource, Default, Debug)]
+pub struct CardResolution {
+    queue: VecDeque<PendingEffect>,
+    awaiting_choice: Option<PendingChoice>,
+}
+
+impl CardResolution {
+    pub fn awaiting_choice(&self) -> Option<&PendingChoice> {
+        self.awaiting_choice.as_ref()
+    }
+
+    fn pending(
+        character_entity: Entity,
+        room_entity: Entity,
+        effects: Vec<CardEffect>,
+    ) -> impl DoubleEndedIterator<Item = PendingEffect> {
+        effects.into_iter().map(move |effect| PendingEffect {
+            character_entity,
+            room_entity,
+            effect,
+        })
+    }
+
+    /// Queue effects to be resolved after everything already queued
+    pub fn push_back(
+        &mut self,
+        character_entity: Entity,
+        room_entity: Entity,
+        effects: Vec<CardEffect>,
+    ) {
+        self.queue
+            .extend(Self::pending(character_entity, room_entity, effects));
+    }
+
+    /// Queue effects to be resolved before anything else, keeping their order
+    pub fn push_front(
+        &mut self,
+        character_entity: Entity,
+        room_entity: Entity,
+        effects: Vec<CardEffect>,
+    ) {
+        for pending in Self::pending(character_entity, room_entity, effects).rev() {
+            self.queue.push_front(pending);
+        }
+    }
+}
+
+/// Asks the player drawing the card to pick one of the options
+#[derive(Event, Debug)]
+pub struct CardPromptEvent {
+    pub character_entity: Entity,
+    pub prompt: String,
+    pub options: Vec<String>,
+}
+
+/// The index of the option picked for the current prompt
+#[derive(Event, Debug)]
+pub struct CardChoiceEvent {
+    pub option: usize,
+}
+
+/// An effect which can't be resolved on the character's traits alone
+#[derive(Event, Debug)]
+pub struct CardEffectEvent {
+    pub character_entity: Entity,
+    pub room_entity: Entity,
+    pub effect: CardEffect,
+}
+
+/// Component for anything spawned by a card
+#[derive(Component)]
+pub struct CardToken;
+
+pub fn queue_drawn_card(
+    mut resolution: ResMut<CardResolution>,
+    mut decks: ResMut<CardDecks>,
+    card_assets: Res<CardAssets>,
+    definitions: Res<Assets<CardDefinitions>>,
+    mut card_drawn_event: EventReader<CardDrawnEvent>,
+    mut inventories: Query<&mut Inventory>,
+    names: Query<&Name>,
+) {
+    let Some(definitions) = definitions.get(&card_assets.definitions) else {
+        return;
+    };
+
+    for event in &mut card_drawn_event.read() {
+        let drawn_by = names
+            .get(event.character_entity)
+            .map(|name| name.as_str())
+            .unwrap_or("Unknown");
+
+        let Some(definition) = definitions.get(event.card) else {
+            continue;
+        };
+
+        info!("{} drew {}", drawn_by, definition.name);
+
+        resolution.push_back(
+            event.character_entity,
+            event.room_entity,
+            definition.effects.clone(),
+        );
+
+        // Omens and items stay with the character, events are done once resolved
+        if let CardType::Event(_) = event.card {
+            decks.discard(event.card);
+            continue;
+        }
+
+        let Ok(mut inventory) = inventories.get_mut(event.character_entity) else {
+            decks.discard(event.card);
+            continue;
+        };
+
+        if let Err(error) = inventory.add(event.card) {
+            info!("{} can't carry {}: {:?}", drawn_by, definition.name, error);
+            decks.discard(event.card);
+        }
+    }
+}
+
+pub fn use_inventory_card(
+    mut resolution: ResMut<CardResolution>,
+    mut decks: ResMut<CardDecks>,
+    card_assets: Res<CardAssets>,
+    definitions: Res<Assets<CardDefinitions>>,
+    mut use_card_event: EventReader<UseCardEvent>,
+    mut characters: Query<(&mut Inventory, Option<&CurrentRoom>)>,
+    turn_state: Res<TurnState>,
+) {
+    let Some(definitions) = definitions.get(&card_assets.definitions) else {
+        return;
+    };
+
+    for UseCardEvent {
+        character_entity,
+        slot,
+    } in &mut use_card_event.read()
+    {
+        // Cards are only used on their owner's turn
+        if !turn_state.is_active(*character_entity) {
+            continue;
+        }
+
+        let Ok((mut inventory, current_room)) = characters.get_mut(*character_entity) else {
+            continue;
+        };
+
+        let Some((card, definition)) = inventory
+            .get(*slot)
+            .and_then(|card| definitions.get(card).map(|definition| (card, definition)))
+        else {
+            continue;
+        };
+
+        if definition.on_use.is_empty() {
+            continue;
+        }
+
+        // Card effects always happen somewhere, a character who never entered a room can't use them
+        let Some(current_room) = current_room else {
+            continue;
+        };
+
+        info!("Using {}", definition.name);
+
+        resolution.push_back(
+            *character_entity,
+            current_room.room_entity,
+            definition.on_use.clone(),
+        );
+
+        match definition.after_use {
+            AfterUse::Keep => {}
+            AfterUse::Discard => {
+                inventory.remove(*slot);
+                decks.discard(card);
+            }
+            AfterUse::RemoveFromGame => {
+                inventory.remove(*slot);
+                decks.remove_from_game(card);
+            }
+        }
+    }
+}
+
+pub fn answer_card_prompt(
+    mut resolution: ResMut<CardResolution>,
+    mut choice_event: EventReader<CardChoiceEvent>,
+) {
+    for CardChoiceEvent { option } in &mut choice_event.read() {
+        let Some(choice) = resolution.awaiting_choice.take() else {
+            continue;
+        };
+
+        let Some(picked) = choice.options.get(*option) else {
+            resolution.awaiting_choice = Some(choice);
+            continue;
+        };
+
+        info!("Picked {}", picked.label);
+
+        let effects = picked.effects.clone();
+        resolution.push_front(choice.character_entity, choice.room_entity, effects);
+    }
+}
+
+pub fn choose_with_number_keys(
+    input: Res<Input<KeyCode>>,
+    resolution: Res<CardResolution>,
+    player: Query<Entity, With<Player>>,
+    mut choice_event: EventWriter<CardChoiceEvent>,
+) {
+    let Some(choice) = resolution.awaiting_choice() else {
+        return;
+    };
+
+    // Other players answer their own prompts
+    if player.get_single().ok() != Some(choice.character_entity) {
+        return;
+    }
+
+    if let Some(option) = CHOICE_KEYS.iter().position(|key| input.just_pressed(*key)) {
+        choice_event.send(CardChoiceEvent { option });
+    }
+}
+
+/// Lists the options of the local player's prompts, to be picked with the number keys
+pub fn show_card_prompt(
+    player: Query<Entity, With<Player>>,
+    mut prompt_event: EventReader<CardPromptEvent>,
+) {
+    for CardPromptEvent {
+        character_entity,
+        prompt,
+        options,
+    } in &mut prompt_event.read()
+    {
+        if player.get_single().ok() != Some(*character_entity) {
+            continue;
+        }
+
+        for (index, option) in options.iter().enumerate() {
+            info!("{}: [{}] {}", prompt, index + 1, option);
+        }
+    }
+}
+
+pub fn resolve_card_effects(
+    mut resolution: ResMut<CardResolution>,
+    haunt: Res<Haunt>,
+    mut dice: ResMut<DiceRng>,
+    mut characters: Query<&mut CharacterTraits>,
+    mut prompt_event: EventWriter<CardPromptEvent>,
+    mut effect_event: EventWriter<CardEffectEvent>,
+    mut dice_rolled_event: EventWriter<DiceRolledEvent>,
+) {
+    while resolution.awaiting_choice.is_none() {
+        let Some(PendingEffect {
+            character_entity,
+            room_entity,
+            effect,
+        }) = resolution.queue.pop_front()
+        else {
+            return;
+        };
+
+        let Ok(mut traits) = characters.get_mut(character_entity) else {
+            continue;
+        };
+
+        let roll = |stat, traits: &CharacterTraits| {
+            let roll = dice.trait_roll(traits, stat, RollModifiers::default());
+            let total = roll.total;
+
+            dice_rolled_event.send(DiceRolledEvent {
+                character_entity,
+                stat: Some(stat),
+                roll,
+            });
+
+            total
+        };
+
+        match effect.resolve(&mut traits, haunt.started, roll) {
+            EffectStep::Done => {}
+            EffectStep::Then(effects) => {
+                resolution.push_front(character_entity, room_entity, effects)
+            }
+            EffectStep::Prompt { prompt, options } => {
+                prompt_event.send(CardPromptEvent {
+                    character_entity,
+                    prompt,
+                    options: options.iter().map(|option| option.label.clone()).collect(),
+                });
+                resolution.awaiting_choice = Some(PendingChoice {
+                    character_entity,
+                    room_entity,
+                    options,
+                });
+            }
+            EffectStep::World(effect) => effect_event.send(CardEffectEvent {
+                character_entity,
+                room_entity,
+                effect,
+            }),
+        }
+    }
+}
+
+pub fn apply_card_effects(
+    mut commands: Commands,
+    mut decks: ResMut<CardDecks>,
+    mut navmesh_grid: ResMut<MeshGrid>,
+    mut effect_event: EventReader<CardEffectEvent>,
+    mut characters: Query<
+        (&mut Transform, Option<&mut Player>, Option<&mut Inventory>),
+        With<CharacterProps>,
+    >,
+    rooms: Query<(&Room, &GridCoords, &RoomLevel)>,
+    mut card_drawn_event: EventWriter<CardDrawnEvent>,
+    mut teleport_event: EventWriter<CharacterTeleportEvent>,
+    mut rotate_room_event: EventWriter<RotateRoomEvent>,
+) {
+    let room_center = |coords: &GridCoords, level: &RoomLevel| {
+        room_location_to_position(coords, level) + Vec2::splat(ROOM_SIZE / 2.)
+    };
+
+    for CardEffectEvent {
+        character_entity,
+        room_entity,
+        effect,
+    } in &mut effect_event.read()
+    {
+        match effect {
+            CardEffect::MoveTo { room: room_name } => {
+                let Some((_, coords, level)) =
+                    rooms.iter().find(|(room, _, _)| &room.name == room_name)
+                else {
+                    info!("{} has not been placed yet", room_name);
+                    continue;
+                };
+
+                let Ok((mut transform, player, _)) = characters.get_mut(*character_entity) else {
+                    continue;
+                };
+
+                transform.translation = room_center(coords, level).extend(transform.translation.z);
+
+                if let Some(mut player) = player {
+                    player.move_path.clear();
+                    player.move_to = None;
+                }
+
+                teleport_event.send(CharacterTeleportEvent {
+                    character_entity: *character_entity,
+                    position: transform.translation,
+                });
+            }
+            CardEffect::GainItem => {
+                let Some(item) = decks.items.draw() else {
+                    info!("The item deck is empty");
+                    continue;
+                };
+
+                card_drawn_event.send(CardDrawnEvent {
+                    character_entity: *character_entity,
+                    room_entity: *room_entity,
+                    card: CardType::Item(item),
+                });
+            }
+            CardEffect::LoseItem(item) => {
+                let Ok((_, _, Some(mut inventory))) = characters.get_mut(*character_entity) else {
+                    continue;
+                };
+
+                if inventory.remove_card(CardType::Item(*item)) {
+                    decks.items.discard(*item);
+                }
+            }
+            CardEffect::Spawn { name } => {
+                let Ok((_, coords, level)) = rooms.get(*room_entity) else {
+                    continue;
+                };
+
+                commands.spawn((
+                    Name::new(name.clone()),
+                    SpatialBundle::from_transform(Transform::from_translation(
+                        room_center(coords, level).extend(2.),
+                    )),
+                    CardToken,
+                ));
+            }
+            CardEffect::RotateRoom { turns } => rotate_room_event.send(RotateRoomEvent {
+                room_entity: *room_entity,
+                turns: *turns,
+            }),
+            CardEffect::SlowRoom { cost } => navmesh_grid.slow_down_room(*room_entity, *cost),
+            CardEffect::ChangeTrait { .. }
+            | CardEffect::TraitRoll { .. }
+            | CardEffect::Choice { .. } => {
+                warn!(
+                    "Skipping {:?}, it is resolved on the character's traits before it gets here",
+                    effect
+                );
+            }
+        }
+    }
+}
@@ -1,4 +1,5 @@
-use crate::components::{room::LDTK_ROOMS, Room};
+use crate::components::card::CardType;
+use crate::components::Room;
 use bevy::prelude::*;
 use bevy_ecs_ldtk::GridCoords;
 use bevy_matchbox::matchbox_socket::PeerId;
@@ -7,25 +8,17 @@ use std::collections::VecDeque;
 
 use super::resources::CharacterFacing;
 
-#[derive(Component)]
+#[derive(Component, Default)]
 pub struct Player {
     pub move_path: VecDeque<GridCoords>,
     pub move_to: Option<GridCoords>,
-    pub in_room: Room,
 }
 
-impl Default for Player {
-    fn default() -> Self {
-        Self {
-            move_path: Default::default(),
-            move_to: Default::default(),
-            in_room: LDTK_ROOMS
-                .iter()
-                .find(|r| r.name.to_lowercase().contains("entry"))
-                .unwrap()
-                .clone(),
-        }
-    }
+/// The room a character is standing in, added the first time the character enters a room
+#[derive(Component, Clone, Debug)]
+pub struct CurrentRoom {
+    pub room_entity: Entity,
+    pub room: Room,
 }
 
 #[derive(Component)]
@@ -33,6 +26,7 @@ pub struct NetworkPlayer {
     pub player_id: PeerId,
 }
 
+/// The current value of each trait, kept in sync with the character's [`CharacterTraits`]
 #[derive(Component, Default, Clone, Debug, Reflect, Serialize, Deserialize)]
 pub struct CharacterProps {
     pub speed: u8,
@@ -41,6 +35,174 @@ pub struct CharacterProps {
     pub knowledge: u8,
 }
 
+impl From<&CharacterTraits> for CharacterProps {
+    fn from(traits: &CharacterTraits) -> Self {
+        Self {
+            speed: traits.speed.value(),
+            might: traits.might.value(),
+            sanity: traits.sanity.value(),
+            knowledge: traits.knowledge.value(),
+        }
+    }
+}
+
+/// One of the four traits of a character
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
+pub enum Trait {
+    Speed,
+    Might,
+    Sanity,
+    Knowledge,
+}
+
+/// The 8 values a trait can take, from lowest to highest, and where the character is on it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+pub struct TraitTrack {
+    values: [u8; 8],
+    /// `-1` once the character has fallen off the bottom of the track
+    index: i8,
+}
+
+impl TraitTrack {
+    pub const fn new(values: [u8; 8], start: i8) -> Self {
+        Self {
+            values,
+            index: start,
+        }
+    }
+
+    pub fn value(&self) -> u8 {
+        usize::try_from(self.index)
+            .map(|index| self.values[index])
+            .unwrap_or(0)
+    }
+
+    /// Move up or down the track. The character can only fall off the bottom once the haunt has
+    /// started, until then the lowest value is as low as a trait goes
+    pub fn shift(&mut self, steps: i8, haunt_started: bool) {
+        let lowest = if haunt_started { -1 } else { 0 };
+        let top = self.values.len() as i8 - 1;
+
+        self.index = self.index.saturating_add(steps).clamp(lowest, top);
+    }
+
+    pub fn fell_off(&self) -> bool {
+        self.index < 0
+    }
+}
+
+#[derive(Component, Debug, Clone, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+pub struct CharacterTraits {
+    pub speed: TraitTrack,
+    pub might: TraitTrack,
+    pub sanity: TraitTrack,
+    pub knowledge: TraitTrack,
+}
+
+impl CharacterTraits {
+    pub fn track(&self, stat: Trait) -> &TraitTrack {
+        match stat {
+            Trait::Speed => &self.speed,
+            Trait::Might => &self.might,
+            Trait::Sanity => &self.sanity,
+            Trait::Knowledge => &self.knowledge,
+        }
+    }
+
+    pub fn track_mut(&mut self, stat: Trait) -> &mut TraitTrack {
+        match stat {
+            Trait::Speed => &mut self.speed,
+            Trait::Might => &mut self.might,
+            Trait::Sanity => &mut self.sanity,
+            Trait::Knowledge => &mut self.knowledge,
+        }
+    }
+
+    pub fn value(&self, stat: Trait) -> u8 {
+        self.track(stat).value()
+    }
+
+    pub fn fell_off(&self) -> bool {
+        [self.speed, self.might, self.sanity, self.knowledge]
+            .iter()
+            .any(TraitTrack::fell_off)
+    }
+}
+
+/// How many items and omens a character can carry
+pub const INVENTORY_CAPACITY: usize = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InventoryError {
+    Full,
+    /// Only items and omens can be carried, events are resolved straight away
+    NotCarriable,
+}
+
+/// The items and omens a character is carrying, in the order they were picked up
+#[derive(Component, Default, Debug, Clone, Serialize, Deserialize)]
+pub struct Inventory {
+    cards: Vec<CardType>,
+}
+
+impl Inventory {
+    pub fn add(&mut self, card: CardType) -> Result<(), InventoryError> {
+        if let CardType::Event(_) = card {
+            return Err(InventoryError::NotCarriable);
+        }
+
+        if self.is_full() {
+            return Err(InventoryError::Full);
+        }
+
+        self.cards.push(card);
+        Ok(())
+    }
+
+    /// Take the card out of the given slot, the slots after it move down by one
+    pub fn remove(&mut self, slot: usize) -> Option<CardType> {
+        (slot < self.cards.len()).then(|| self.cards.remove(slot))
+    }
+
+    /// Take the first copy of a card out of the inventory
+    pub fn remove_card(&mut self, card: CardType) -> bool {
+        let Some(slot) = self.cards.iter().position(|carried| *carried == card) else {
+            return false;
+        };
+
+        self.cards.remove(slot);
+        true
+    }
+
+    pub fn get(&self, slot: usize) -> Option<CardType> {
+        self.cards.get(slot).copied()
+    }
+
+    pub fn contains(&self, card: CardType) -> bool {
+        self.cards.contains(&card)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &CardType> {
+        self.cards.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.cards.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cards.is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.cards.len() >= INVENTORY_CAPACITY
+    }
+}
+
+/// Marks a character who has died during the haunt
+#[derive(Component)]
+pub struct Dead;
+
 #[derive(Component, Default)]
 pub struct NetworkTransform {
     pub move_path: VecDeque<GridCoords>,
@@ -55,3 +217,52 @@ pub struct AnimationTimer {
     pub cols: usize,
     pub facing: CharacterFacing,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VALUES: [u8; 8] = [2, 3, 3, 4, 5, 6, 6, 7];
+
+    #[test]
+    fn shift_stops_at_the_lowest_value_before_the_haunt() {
+        let mut track = TraitTrack::new(VALUES, 2);
+
+        track.shift(-1, false);
+        assert_eq!(track.value(), 3);
+
+        track.shift(-5, false);
+        assert_eq!(track.value(), 2);
+        assert!(!track.fell_off());
+
+        track.shift(i8::MIN, false);
+        assert_eq!(track.value(), 2);
+        assert!(!track.fell_off());
+    }
+
+    #[test]
+    fn shift_falls_off_the_track_after_the_haunt() {
+        let mut track = TraitTrack::new(VALUES, 2);
+
+        track.shift(-3, true);
+        assert_eq!(track.value(), 0);
+        assert!(track.fell_off());
+
+        // Never further than one below the lowest value
+        track.shift(i8::MIN, true);
+        track.shift(1, true);
+        assert_eq!(track.value(), 2);
+        assert!(!track.fell_off());
+    }
+
+    #[test]
+    fn shift_stops_at_the_highest_value() {
+        let mut track = TraitTrack::new(VALUES, 5);
+
+        track.shift(i8::MAX, false);
+        assert_eq!(track.value(), 7);
+
+        track.shift(-1, false);
+        assert_eq!(track.value(), 6);
+    }
+}
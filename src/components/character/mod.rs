@@ -9,7 +9,7 @@ use bevy_asset_loader::prelude::*;
 pub use components::*;
 use leafwing_input_manager::prelude::*;
 use resources::*;
-pub use resources::{CharacterInput, CharacterType, CharacterWalk};
+pub use resources::{CharacterInput, CharacterType, CharacterWalk, Headshots, SelectedCharacter};
 pub use systems::*;
 
 const CHARACTER_MOVE_SPEED: f32 = 45.0;
@@ -20,6 +20,9 @@ impl Plugin for CharacterPlugin {
         app.add_collection_to_loading_state::<_, CharacterWalk>(GameState::Loading)
             .add_collection_to_loading_state::<_, Headshots>(GameState::Loading)
             .register_type::<CharacterProps>()
+            .register_type::<CharacterTraits>()
+            .init_resource::<MovementPreview>()
+            .init_resource::<SelectedCharacter>()
             .add_plugins(InputManagerPlugin::<CharacterInput>::default())
             .add_systems(
                 OnEnter(GameState::InitialSpawn),
@@ -44,11 +47,18 @@ impl Plugin for CharacterPlugin {
                 Update,
                 check_pathfinding_answer.run_if(in_state(GameState::Main)),
             )
+            .add_systems(
+                Update,
+                (update_movement_preview, draw_movement_preview)
+                    .chain()
+                    .run_if(in_state(GameState::Main)),
+            )
             .add_systems(
                 Update,
                 (move_player, move_network_player)
                     .run_if(in_state(GameState::Main).or_else(in_state(GameState::Paused))),
             )
+            .add_systems(Update, (sync_character_props, check_character_death))
             .add_systems(OnExit(GameState::Main), on_main_exit);
     }
 }
@@ -1,23 +1,70 @@
 use std::ops::Mul;
 
+use super::components::{CharacterTraits, TraitTrack};
+use crate::components::ReachableTiles;
 use bevy::prelude::*;
 use bevy_asset_loader::prelude::*;
+use bevy_ecs_ldtk::GridCoords;
 use leafwing_input_manager::prelude::*;
 use serde::{Deserialize, Serialize};
 
-#[derive(Hash, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[derive(Hash, PartialEq, Eq, Debug, Clone, Copy, Default, Serialize, Deserialize)]
 pub enum CharacterType {
+    #[default]
     Professor,
     Fbi,
 }
 
+impl CharacterType {
+    /// Every character which can be played, in the order they are shown to pick from
+    pub const ALL: [CharacterType; 2] = [CharacterType::Professor, CharacterType::Fbi];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CharacterType::Professor => "The Professor",
+            CharacterType::Fbi => "The FBI Agent",
+        }
+    }
+
+    /// The trait tracks and starting positions printed on the character's card
+    pub fn traits(&self) -> CharacterTraits {
+        match self {
+            CharacterType::Professor => CharacterTraits {
+                speed: TraitTrack::new([2, 3, 3, 4, 5, 5, 6, 6], 3),
+                might: TraitTrack::new([1, 2, 3, 4, 4, 5, 5, 6], 2),
+                sanity: TraitTrack::new([3, 3, 4, 5, 5, 6, 7, 7], 2),
+                knowledge: TraitTrack::new([4, 5, 5, 5, 5, 6, 7, 8], 3),
+            },
+            CharacterType::Fbi => CharacterTraits {
+                speed: TraitTrack::new([3, 4, 4, 4, 5, 6, 7, 7], 2),
+                might: TraitTrack::new([3, 4, 4, 5, 5, 6, 6, 7], 3),
+                sanity: TraitTrack::new([2, 3, 3, 4, 5, 5, 6, 7], 3),
+                knowledge: TraitTrack::new([2, 3, 3, 4, 5, 5, 6, 6], 2),
+            },
+        }
+    }
+}
+
 #[derive(Actionlike, Reflect, Clone, Hash, PartialEq, Eq)]
 pub enum CharacterInput {
     TogglePause,
     RotateRoom,
+    TakeStairs,
     WalkSelect,
     SelectObject,
     MoveCamera,
+    EndTurn,
+}
+
+/// Where the player can still walk this turn, and the path to the tile under the mouse
+#[derive(Resource, Default)]
+pub struct MovementPreview {
+    pub reachable: Option<ReachableTiles>,
+    pub budget: u32,
+    /// The navmesh generation the reachable tiles were found on
+    pub generation: u64,
+    pub hovered: Option<GridCoords>,
+    pub path: Vec<GridCoords>,
 }
 
 #[derive(AssetCollection, Resource)]
@@ -30,6 +77,15 @@ pub struct CharacterWalk {
     pub fbi: Handle<TextureAtlas>,
 }
 
+impl CharacterWalk {
+    pub fn sheet(&self, character: CharacterType) -> Handle<TextureAtlas> {
+        match character {
+            CharacterType::Professor => self.professor.clone(),
+            CharacterType::Fbi => self.fbi.clone(),
+        }
+    }
+}
+
 #[derive(AssetCollection, Resource)]
 pub struct Headshots {
     #[asset(path = "sprites/professor_headshot.png")]
@@ -38,6 +94,19 @@ pub struct Headshots {
     pub fbi_headshot: Handle<Image>,
 }
 
+impl Headshots {
+    pub fn headshot(&self, character: CharacterType) -> Handle<Image> {
+        match character {
+            CharacterType::Professor => self.professor_headshot.clone(),
+            CharacterType::Fbi => self.fbi_headshot.clone(),
+        }
+    }
+}
+
+/// The character the local player plays as, picked in the lobby
+#[derive(Resource, Default, Debug)]
+pub struct SelectedCharacter(pub CharacterType);
+
 #[repr(usize)]
 #[derive(Debug, Clone, Copy, Default, PartialEq)]
 #[allow(dead_code)]
@@ -1,10 +1,15 @@
 use std::collections::VecDeque;
 
+use crate::components::room::{CharacterTeleportEvent, RoomLevel};
+use crate::components::Haunt;
+use crate::components::MeshGrid;
 use crate::components::MouseToWorldCoords;
 use crate::components::MoveRequest;
 use crate::components::NavmeshAnswerEvent;
 use crate::components::Selectable;
-use crate::components::INT_TILE_SIZE;
+use crate::components::TurnState;
+use crate::components::{tile_to_world, world_to_tile, INT_TILE_SIZE};
+use crate::events::GameEvent;
 use crate::GameState;
 
 use super::components::*;
@@ -17,9 +22,14 @@ use bevy_ecs_ldtk::GridCoords;
 use bevy_matchbox::matchbox_socket::PeerId;
 use bevy_rapier2d::prelude::*;
 use leafwing_input_manager::prelude::*;
-use rand::prelude::*;
 
-pub fn spawn_character_player(mut commands: Commands, asset: Res<CharacterWalk>) {
+pub fn spawn_character_player(
+    mut commands: Commands,
+    asset: Res<CharacterWalk>,
+    selected: Res<SelectedCharacter>,
+) {
+    let traits = selected.0.traits();
+
     let sprite = TextureAtlasSprite {
         custom_size: Some(Vec2::splat(25.)),
         index: CharacterFacing::Right * 9usize,
@@ -34,7 +44,7 @@ pub fn spawn_character_player(mut commands: Commands, asset: Res<CharacterWalk>)
     commands
         .spawn((
             SpriteSheetBundle {
-                texture_atlas: asset.professor.clone(),
+                texture_atlas: asset.sheet(selected.0),
                 sprite,
                 transform: Transform::from_xyz(48., 48., 2.),
                 ..default()
@@ -49,19 +59,17 @@ pub fn spawn_character_player(mut commands: Commands, asset: Res<CharacterWalk>)
             Name::new("Character"),
             GravityScale(0.),
             Player::default(),
-            CharacterProps {
-                knowledge: rand::thread_rng().gen_range(2..11),
-                might: rand::thread_rng().gen_range(2..11),
-                sanity: rand::thread_rng().gen_range(2..11),
-                speed: rand::thread_rng().gen_range(2..11),
-            },
+            CharacterProps::from(&traits),
+            traits,
             GridCoords { x: 0, y: 0 },
             InputManagerBundle::<CharacterInput> {
                 input_map: InputMap::default()
                     .insert(KeyCode::Escape, CharacterInput::TogglePause)
                     .insert(KeyCode::R, CharacterInput::RotateRoom)
+                    .insert(KeyCode::E, CharacterInput::TakeStairs)
                     .insert(MouseButton::Right, CharacterInput::WalkSelect)
                     .insert(MouseButton::Left, CharacterInput::SelectObject)
+                    .insert(KeyCode::Space, CharacterInput::EndTurn)
                     .insert(
                         VirtualDPad {
                             up: KeyCode::W.into(),
@@ -81,14 +89,17 @@ pub fn spawn_character_player(mut commands: Commands, asset: Res<CharacterWalk>)
             ActiveEvents::COLLISION_EVENTS,
             Collider::compound(vec![(Vec2::new(0., 2.), 0., Collider::cuboid(4., 2.))]),
         ))
-        .insert((Selectable,));
+        .insert((Selectable, Inventory::default()));
 }
 
 pub fn spawn_network_player(
     commands: &mut Commands,
     asset: &Res<CharacterWalk>,
     player_id: PeerId,
+    character: CharacterType,
 ) {
+    let traits = character.traits();
+
     let sprite = TextureAtlasSprite {
         custom_size: Some(Vec2::splat(25.)),
         index: CharacterFacing::Right * 9usize,
@@ -99,7 +110,7 @@ pub fn spawn_network_player(
     commands
         .spawn((
             SpriteSheetBundle {
-                texture_atlas: asset.fbi.clone(),
+                texture_atlas: asset.sheet(character),
                 sprite,
                 transform: Transform::from_xyz(48., 48., 2.),
                 ..default()
@@ -111,7 +122,8 @@ pub fn spawn_network_player(
                 cols: 9,
                 facing: CharacterFacing::Right,
             },
-            CharacterProps::default(),
+            CharacterProps::from(&traits),
+            traits,
             Name::new("Character"),
             GravityScale(0.),
             GridCoords { x: 0, y: 0 },
@@ -126,7 +138,7 @@ pub fn spawn_network_player(
                 ..Default::default()
             },
         ))
-        .insert(Selectable);
+        .insert((Selectable, Inventory::default()));
 }
 
 pub fn on_main_exit(mut player_velocity: Query<&mut Velocity, With<Player>>) {
@@ -223,17 +235,17 @@ pub fn listen_for_pause(
 }
 
 pub fn move_player(
-    mut player_query: Query<(&mut Velocity, &mut Player, &Transform), With<Player>>,
+    mut player_query: Query<(Entity, &mut Velocity, &mut Player, &mut Transform), With<Player>>,
     time: Res<Time>,
+    mut teleport_event: EventWriter<CharacterTeleportEvent>,
 ) {
-    let Ok((mut velocity, mut player, player_transform)) = player_query.get_single_mut() else {
+    let Ok((player_entity, mut velocity, mut player, mut player_transform)) =
+        player_query.get_single_mut()
+    else {
         return;
     };
 
-    let current_grid = GridCoords::new(
-        (player_transform.translation.x / INT_TILE_SIZE).round() as i32,
-        (player_transform.translation.y / INT_TILE_SIZE).round() as i32,
-    );
+    let current_grid = world_to_tile(player_transform.translation.truncate());
 
     if player.move_to.is_none() && player.move_path.len() > 0 {
         player.move_to = player.move_path.pop_front();
@@ -250,25 +262,38 @@ pub fn move_player(
         return;
     }
 
-    let direction = (Vec2::new(path.x as f32 * INT_TILE_SIZE, path.y as f32 * INT_TILE_SIZE)
-        - player_transform.translation.truncate())
-    .normalize();
+    let target = tile_to_world(path);
+
+    // The path goes up or down the stairs
+    if takes_stairs(player_transform.translation.truncate(), target) {
+        player_transform.translation = target.extend(player_transform.translation.z);
+        velocity.linvel = Vec2::ZERO;
+
+        teleport_event.send(CharacterTeleportEvent {
+            character_entity: player_entity,
+            position: player_transform.translation,
+        });
+        return;
+    }
+
+    let direction = (target - player_transform.translation.truncate()).normalize();
 
     velocity.linvel = direction * time.delta_seconds() * CHARACTER_MOVE_SPEED * 100.;
 }
 
+fn takes_stairs(from: Vec2, to: Vec2) -> bool {
+    RoomLevel::from_world_position(from) != RoomLevel::from_world_position(to)
+}
+
 pub fn move_network_player(
     mut player_query: Query<
-        (&mut Velocity, &mut NetworkTransform, &Transform),
+        (&mut Velocity, &mut NetworkTransform, &mut Transform),
         With<NetworkTransform>,
     >,
     time: Res<Time>,
 ) {
-    for (mut velocity, mut player, player_transform) in &mut player_query {
-        let current_grid = GridCoords::new(
-            (player_transform.translation.x / INT_TILE_SIZE).round() as i32,
-            (player_transform.translation.y / INT_TILE_SIZE).round() as i32,
-        );
+    for (mut velocity, mut player, mut player_transform) in &mut player_query {
+        let current_grid = world_to_tile(player_transform.translation.truncate());
 
         if player.move_to.is_none() && player.move_path.len() > 0 {
             player.move_to = player.move_path.pop_front();
@@ -276,18 +301,24 @@ pub fn move_network_player(
 
         let Some(path) = player.move_to else {
             velocity.linvel = Vec2::ZERO;
-            return;
+            continue;
         };
 
         if path == current_grid {
             player.move_to = None;
             velocity.linvel = Vec2::ZERO;
-            return;
+            continue;
+        }
+
+        let target = tile_to_world(path);
+
+        if takes_stairs(player_transform.translation.truncate(), target) {
+            player_transform.translation = target.extend(player_transform.translation.z);
+            velocity.linvel = Vec2::ZERO;
+            continue;
         }
 
-        let direction = (Vec2::new(path.x as f32 * INT_TILE_SIZE, path.y as f32 * INT_TILE_SIZE)
-            - player_transform.translation.truncate())
-        .normalize();
+        let direction = (target - player_transform.translation.truncate()).normalize();
 
         velocity.linvel = direction * time.delta_seconds() * CHARACTER_MOVE_SPEED * 100.;
     }
@@ -296,38 +327,111 @@ pub fn move_network_player(
 pub fn request_pathfinding(
     mouse: Res<MouseToWorldCoords>,
     player_input: Query<(&ActionState<CharacterInput>, &Transform, Entity), With<Player>>,
+    turn_state: Res<TurnState>,
     mut pathfinding_request: EventWriter<MoveRequest>,
 ) {
     let Ok((character_input, character_position, player_entity)) = player_input.get_single() else {
         return;
     };
 
+    // Only the character whose turn it is can walk, and only as far as their speed allows
+    if !turn_state.is_active(player_entity) || turn_state.movement_left() == 0 {
+        return;
+    }
+
     let Some(mouse_pos) = mouse.0 else {
         return;
     };
 
     if character_input.just_pressed(CharacterInput::WalkSelect) {
-        let pos = character_position.translation.truncate();
-        let mouse_tile_pos = GridCoords::new(
-            (mouse_pos.x.round() / INT_TILE_SIZE) as i32,
-            (mouse_pos.y.round() / INT_TILE_SIZE) as i32,
-        );
-
-        let char_position = GridCoords::new(
-            (pos.x.round() / INT_TILE_SIZE) as i32,
-            (pos.y.round() / INT_TILE_SIZE) as i32,
-        );
-
         pathfinding_request.send(MoveRequest {
             requesting_entity: player_entity,
-            move_from: char_position,
-            move_to: mouse_tile_pos,
+            move_from: world_to_tile(character_position.translation.truncate()),
+            move_to: world_to_tile(mouse_pos),
         });
     }
 }
 
+pub fn update_movement_preview(
+    mut preview: ResMut<MovementPreview>,
+    mouse: Res<MouseToWorldCoords>,
+    turn_state: Res<TurnState>,
+    navmesh_grid: Res<MeshGrid>,
+    player: Query<(Entity, &Player, &Transform)>,
+) {
+    let Ok((player_entity, player, transform)) = player.get_single() else {
+        return;
+    };
+
+    // Nothing to preview while walking, or when it is not the player's turn
+    if !turn_state.is_active(player_entity) || !player.move_path.is_empty() {
+        *preview = MovementPreview::default();
+        return;
+    }
+
+    let origin = world_to_tile(transform.translation.truncate());
+    let budget = turn_state.movement_left();
+    let generation = navmesh_grid.generation();
+
+    let stale = match &preview.reachable {
+        Some(reachable) => {
+            reachable.origin() != origin
+                || preview.budget != budget
+                || preview.generation != generation
+        }
+        None => true,
+    };
+    if stale {
+        preview.reachable = navmesh_grid.reachable(origin, budget);
+        preview.budget = budget;
+        preview.generation = generation;
+        preview.hovered = None;
+    }
+
+    let hovered = mouse.0.map(world_to_tile).filter(|tile| {
+        preview
+            .reachable
+            .as_ref()
+            .is_some_and(|r| r.contains(*tile))
+    });
+    if hovered == preview.hovered {
+        return;
+    }
+
+    preview.hovered = hovered;
+    preview.path = hovered
+        .and_then(|hovered| navmesh_grid.find_path(origin, hovered))
+        .map(|path| navmesh_grid.smooth_path(&path))
+        .unwrap_or_default();
+}
+
+pub fn draw_movement_preview(mut gizmos: Gizmos, preview: Res<MovementPreview>) {
+    let Some(reachable) = &preview.reachable else {
+        return;
+    };
+
+    for (coords, _) in reachable.iter() {
+        gizmos.rect_2d(
+            tile_to_world(coords),
+            0.,
+            Vec2::splat(INT_TILE_SIZE - 2.),
+            Color::rgba(0.3, 0.6, 1., 0.3),
+        );
+    }
+
+    for step in preview.path.windows(2) {
+        gizmos.line_2d(
+            tile_to_world(step[0]),
+            tile_to_world(step[1]),
+            Color::rgba(1., 1., 1., 0.5),
+        );
+    }
+}
+
 pub fn check_pathfinding_answer(
     mut gizmos: Gizmos,
+    turn_state: Res<TurnState>,
+    navmesh_grid: Res<MeshGrid>,
     mut pathfinding_event_received: EventReader<NavmeshAnswerEvent>,
     mut player: Query<(Entity, &mut Player), With<Player>>,
 ) {
@@ -340,7 +444,9 @@ pub fn check_pathfinding_answer(
             continue;
         }
         if let Ok(path) = &pathfinding_event.path {
-            let mut path: VecDeque<_> = path.clone().into();
+            let mut path = path.clone();
+            navmesh_grid.truncate_path(&mut path, turn_state.movement_left());
+            let mut path: VecDeque<_> = navmesh_grid.smooth_path(&path).into();
             player.move_path.clear();
             player.move_path.append(&mut path);
         } else {
@@ -348,14 +454,8 @@ pub fn check_pathfinding_answer(
         }
     }
 
-    let mut start_option = player
-        .move_path
-        .get(0)
-        .map(|g| Vec2::new(g.x as f32, g.y as f32));
-    let mut end_option = player
-        .move_path
-        .get(1)
-        .map(|g| Vec2::new(g.x as f32, g.y as f32));
+    let mut start_option = player.move_path.get(0).map(|g| tile_to_world(*g));
+    let mut end_option = player.move_path.get(1).map(|g| tile_to_world(*g));
     let mut end_index = 1usize;
 
     while start_option.is_some() && end_option.is_some() {
@@ -363,8 +463,8 @@ pub fn check_pathfinding_answer(
         let end = end_option.unwrap();
 
         gizmos.line_2d(
-            start * INT_TILE_SIZE,
-            end * INT_TILE_SIZE,
+            start,
+            end,
             Color::Rgba {
                 red: 1.,
                 green: 1.,
@@ -373,14 +473,36 @@ pub fn check_pathfinding_answer(
             },
         );
 
-        start_option = player
-            .move_path
-            .get(end_index)
-            .map(|g| Vec2::new(g.x as f32, g.y as f32));
+        start_option = player.move_path.get(end_index).map(|g| tile_to_world(*g));
         end_index += 1;
-        end_option = player
-            .move_path
-            .get(end_index)
-            .map(|g| Vec2::new(g.x as f32, g.y as f32));
+        end_option = player.move_path.get(end_index).map(|g| tile_to_world(*g));
+    }
+}
+
+pub fn sync_character_props(
+    mut characters: Query<(&CharacterTraits, &mut CharacterProps), Changed<CharacterTraits>>,
+) {
+    for (traits, mut props) in &mut characters {
+        *props = CharacterProps::from(traits);
+    }
+}
+
+pub fn check_character_death(
+    mut commands: Commands,
+    haunt: Res<Haunt>,
+    characters: Query<(Entity, &CharacterTraits), (Changed<CharacterTraits>, Without<Dead>)>,
+    mut game_event: EventWriter<GameEvent>,
+) {
+    if !haunt.started {
+        return;
+    }
+
+    for (character_entity, traits) in &characters {
+        if !traits.fell_off() {
+            continue;
+        }
+
+        commands.entity(character_entity).insert(Dead);
+        game_event.send(GameEvent::Death { character_entity });
     }
 }
@@ -0,0 +1,173 @@
+use super::character::{CharacterTraits, Trait};
+use super::network::{shared_seed, NetworkedLobby, RandomStream};
+use crate::GameState;
+use bevy::prelude::*;
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// The faces of a Betrayal die
+pub const DIE_FACES: [u8; 6] = [0, 0, 1, 1, 2, 2];
+
+/// No roll can use more dice than this, whatever the trait value and modifiers
+pub const MAX_DICE: u8 = 8;
+
+pub struct DicePlugin;
+
+impl Plugin for DicePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<DiceRolledEvent>()
+            .add_systems(OnEnter(GameState::InitialSpawn), seed_dice)
+            .add_systems(Update, log_dice_rolls);
+    }
+}
+
+/// The only source of randomness for dice rolls, so peers sharing a seed roll the same dice
+#[derive(Resource)]
+pub struct DiceRng {
+    seed: u64,
+    rng: StdRng,
+}
+
+impl DiceRng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn roll(&mut self, dice: u8) -> Vec<u8> {
+        (0..dice.min(MAX_DICE))
+            .map(|_| *DIE_FACES.choose(&mut self.rng).unwrap())
+            .collect()
+    }
+
+    /// Roll as many dice as the character's trait value, adjusted by the modifiers
+    pub fn trait_roll(
+        &mut self,
+        traits: &CharacterTraits,
+        stat: Trait,
+        modifiers: RollModifiers,
+    ) -> DiceRoll {
+        let dice =
+            (traits.value(stat) as i16 + modifiers.extra_dice as i16).clamp(0, MAX_DICE as i16);
+
+        DiceRoll::new(self.roll(dice as u8), modifiers.bonus)
+    }
+}
+
+/// Changes applied to a trait roll by items, omens or the haunt
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RollModifiers {
+    pub extra_dice: i8,
+    pub bonus: i8,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiceRoll {
+    pub dice: Vec<u8>,
+    pub bonus: i8,
+    pub total: u32,
+}
+
+impl DiceRoll {
+    pub fn new(dice: Vec<u8>, bonus: i8) -> Self {
+        let sum = dice.iter().map(|die| *die as i32).sum::<i32>();
+
+        Self {
+            total: (sum + bonus as i32).max(0) as u32,
+            dice,
+            bonus,
+        }
+    }
+}
+
+/// Sent for every roll made, so the roll behind each outcome ends up in the log
+#[derive(Event, Debug, Clone)]
+pub struct DiceRolledEvent {
+    pub character_entity: Entity,
+    pub stat: Option<Trait>,
+    pub roll: DiceRoll,
+}
+
+fn seed_dice(mut commands: Commands, lobby: Option<Res<NetworkedLobby>>) {
+    let seed = shared_seed(lobby.as_deref(), RandomStream::Dice);
+    info!("Seeding the dice with {}", seed);
+
+    commands.insert_resource(DiceRng::new(seed));
+}
+
+fn log_dice_rolls(mut rolls: EventReader<DiceRolledEvent>, names: Query<&Name>) {
+    for DiceRolledEvent {
+        character_entity,
+        stat,
+        roll,
+    } in &mut rolls.read()
+    {
+        let who = names.get(*character_entity).map_or_else(
+            |_| format!("{:?}", character_entity),
+            |name| name.to_string(),
+        );
+
+        match stat {
+            Some(stat) => info!(
+                "{} rolled {:?} for {:?}, {} in total",
+                who, roll.dice, stat, roll.total
+            ),
+            None => info!("{} rolled {:?}, {} in total", who, roll.dice, roll.total),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn faces_are_blank_one_or_two() {
+        let mut rng = DiceRng::new(1);
+        let faces = (0..100)
+            .flat_map(|_| rng.roll(MAX_DICE))
+            .collect::<Vec<_>>();
+
+        assert_eq!(faces.len(), 100 * MAX_DICE as usize);
+        assert!(faces.iter().all(|face| *face <= 2));
+        for face in [0, 1, 2] {
+            assert!(faces.contains(&face), "never rolled a {}", face);
+        }
+    }
+
+    #[test]
+    fn no_more_than_max_dice() {
+        assert_eq!(DiceRng::new(1).roll(MAX_DICE + 4).len(), MAX_DICE as usize);
+        assert!(DiceRng::new(1).roll(0).is_empty());
+    }
+
+    #[test]
+    fn same_seed_same_rolls() {
+        let mut a = DiceRng::new(42);
+        let mut b = DiceRng::new(42);
+
+        for dice in 0..=MAX_DICE {
+            assert_eq!(a.roll(dice), b.roll(dice));
+        }
+
+        let rolls = |seed| {
+            let mut rng = DiceRng::new(seed);
+            (0..10).map(|_| rng.roll(MAX_DICE)).collect::<Vec<_>>()
+        };
+        assert_eq!(rolls(42), rolls(42));
+        assert_ne!(rolls(42), rolls(43));
+    }
+
+    #[test]
+    fn total_adds_the_bonus_without_going_below_zero() {
+        assert_eq!(DiceRoll::new(vec![2, 1, 0], 0).total, 3);
+        assert_eq!(DiceRoll::new(vec![2, 1, 0], 2).total, 5);
+        assert_eq!(DiceRoll::new(vec![1], -3).total, 0);
+    }
+}
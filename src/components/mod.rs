@@ -1,12 +1,14 @@
+use crate::events::GameEvent;
 use bevy::prelude::*;
 
 mod camera;
-#[allow(dead_code)]
 mod card;
 mod character;
+mod dice;
 mod navmesh;
 mod network;
 mod room;
+mod turn;
 
 #[derive(Component, Default)]
 pub struct Selectable;
@@ -14,14 +16,27 @@ pub struct Selectable;
 #[derive(Resource, Default)]
 pub struct MouseToWorldCoords(Option<Vec2>);
 
-pub use character::spawn_character_player;
+/// Game wide state of the haunt. Characters can only die once it has started
+#[derive(Resource, Default)]
+pub struct Haunt {
+    pub started: bool,
+}
+
+pub use card::{CardAssets, CardDefinitions, UseCardEvent};
+pub use character::{
+    spawn_character_player, CharacterType, Headshots, Inventory, Player, INVENTORY_CAPACITY,
+};
 pub use navmesh::{
-    MoveRequest, NavmeshAnswerEvent, NavmeshBundle, NavmeshTileBundle, RebuildNavmesh,
-    WalkableState,
+    MeshGrid, MoveRequest, NavChunk, NavmeshAnswerEvent, ReachableTiles, RoomLinks, WalkableState,
+    DIFFICULT_COST, DOORWAY_COST, FLOOR_COST, ROOM_CROSSING_COST,
+};
+pub use network::{
+    shared_seed, LobbyAction, LobbyConfig, LobbyPeers, NetworkedLobby, RandomStream,
+    StartMultiplayer, MAX_PLAYERS, PLAYER_COUNT_PICKABLE,
 };
-pub use network::{LobbyConfig, StartMultiplayer};
 pub use room::setup_first_rooms;
-pub use room::{Room, INT_TILE_SIZE, ROOM_SIZE};
+pub use room::{tile_to_world, world_to_tile, Room, INT_TILE_SIZE, ROOM_SIZE};
+pub use turn::{EndTurnEvent, TurnState};
 
 pub struct ComponentPlugin;
 
@@ -33,7 +48,12 @@ impl Plugin for ComponentPlugin {
             navmesh::NavmeshPlugin,
             camera::CameraPlugin,
             network::NetworkPlugin,
+            card::CardPlugin,
+            dice::DicePlugin,
+            turn::TurnPlugin,
         ))
-        .init_resource::<MouseToWorldCoords>();
+        .add_event::<GameEvent>()
+        .init_resource::<MouseToWorldCoords>()
+        .init_resource::<Haunt>();
     }
 }
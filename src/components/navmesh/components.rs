@@ -1,30 +1,10 @@
 use bevy::{prelude::*, tasks::Task};
-use bevy_ecs_ldtk::prelude::*;
 
-use super::NavmeshAnswerEvent;
-
-#[derive(Component, Clone, Eq, PartialEq)]
-pub enum WalkableState {
-    NotWalkable,
-    Walkable,
-}
-
-#[derive(Component, Default)]
-pub struct NavmeshParent;
-
-#[derive(Bundle, Default)]
-pub struct NavmeshBundle {
-    pub transform: TransformBundle,
-    pub name: Name,
-    pub navmesh: NavmeshParent,
-}
-
-#[derive(Bundle)]
-pub struct NavmeshTileBundle {
-    pub walkable: WalkableState,
-    pub grid_coord: GridCoords,
-    pub transform: TransformBundle,
-}
+use super::{MoveRequest, NavmeshAnswerEvent};
 
 #[derive(Component)]
-pub struct PathfindingTask(pub Task<NavmeshAnswerEvent>);
+pub struct PathfindingTask {
+    pub request: MoveRequest,
+    /// The answer, along with the generation of the navmesh it was found on
+    pub task: Task<(u64, NavmeshAnswerEvent)>,
+}
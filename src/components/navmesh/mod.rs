@@ -2,12 +2,14 @@ mod components;
 mod systems;
 
 use bevy::prelude::*;
-use bevy::utils::HashMap;
 use bevy_ecs_ldtk::prelude::*;
 use std::sync::{Arc, RwLock};
 use systems::*;
-
-pub use components::{NavmeshBundle, NavmeshParent, NavmeshTileBundle, WalkableState};
+use the_haunted_mansion::navigation::NavGrid;
+pub use the_haunted_mansion::navigation::{
+    NavChunk, ReachableTiles, RoomLinks, WalkableState, DIFFICULT_COST, DOORWAY_COST, FLOOR_COST,
+    ROOM_CROSSING_COST,
+};
 
 pub struct NavmeshPlugin;
 
@@ -15,19 +17,90 @@ impl Plugin for NavmeshPlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<NavmeshAnswerEvent>()
             .add_event::<MoveRequest>()
-            .add_event::<RebuildNavmesh>()
             .init_resource::<MeshGrid>()
-            .add_systems(Update, update_navmesh)
             .add_systems(Update, listen_for_navmesh_requests)
-            .add_systems(Update, rebuild_navmesh)
             .add_systems(Update, debug_tiles)
             .add_systems(Update, poll_for_pathfinding_completion);
     }
 }
 
-#[derive(Resource, Default)]
+/// Whether characters can walk diagonally between tiles, or only up, down, left and right
+pub const DIAGONAL_MOVEMENT: bool = true;
+
+#[derive(Resource)]
 pub struct MeshGrid {
-    grids_and_weights: Arc<RwLock<HashMap<GridCoords, WalkableState>>>,
+    grid: Arc<RwLock<NavGrid>>,
+}
+
+impl Default for MeshGrid {
+    fn default() -> Self {
+        let mut grid = NavGrid::default();
+        grid.set_diagonals(DIAGONAL_MOVEMENT);
+
+        Self {
+            grid: Arc::new(RwLock::new(grid)),
+        }
+    }
+}
+
+impl MeshGrid {
+    /// Replace the navmesh of a room, which is now at the given world room coordinates
+    pub fn insert_room(&mut self, room_entity: Entity, room_coords: GridCoords, chunk: NavChunk) {
+        self.grid
+            .write()
+            .unwrap()
+            .insert_chunk(room_entity, room_coords, chunk);
+    }
+
+    /// Makes walking anywhere in the room cost at least `cost` for the rest of the game
+    pub fn slow_down_room(&mut self, room_entity: Entity, cost: u8) {
+        self.grid.write().unwrap().slow_down_room(room_entity, cost);
+    }
+
+    pub fn remove_room(&mut self, room_entity: Entity) {
+        self.grid.write().unwrap().remove_chunk(room_entity);
+    }
+
+    /// Changes whenever any room's tiles are added, removed or replaced
+    pub fn generation(&self) -> u64 {
+        self.grid.read().map(|grid| grid.generation()).unwrap_or(0)
+    }
+
+    pub fn tile(&self, coords: GridCoords) -> Option<WalkableState> {
+        self.grid.read().ok()?.get(coords)
+    }
+
+    /// Every tile which can be walked to from `from` with at most `budget` movement
+    pub fn reachable(&self, from: GridCoords, budget: u32) -> Option<ReachableTiles> {
+        Some(self.grid.read().ok()?.reachable(from, budget))
+    }
+
+    /// The same path a `MoveRequest` would answer with, found right away instead of on a task
+    pub fn find_path(&self, from: GridCoords, to: GridCoords) -> Option<Vec<GridCoords>> {
+        self.grid.read().ok()?.find_path(from, to)
+    }
+
+    /// The tile taking the stairs from floor `from` to floor `to` lands on, the same one paths
+    /// going up or down the stairs jump to
+    pub fn stairs_landing(&self, from: i8, to: i8) -> Option<GridCoords> {
+        let grid = self.grid.read().ok()?;
+        grid.stairs_tile(grid.stairs_destination(from, to)?)
+    }
+
+    /// Shortens a path to where the movement runs out, the first tile is where the walk starts
+    pub fn truncate_path(&self, path: &mut Vec<GridCoords>, budget: u32) {
+        if let Ok(grid) = self.grid.read() {
+            grid.truncate_path(path, budget);
+        }
+    }
+
+    /// The waypoints of a tile path, only where it has to turn
+    pub fn smooth_path(&self, path: &[GridCoords]) -> Vec<GridCoords> {
+        match self.grid.read() {
+            Ok(grid) => grid.smooth_path(path),
+            Err(_) => path.to_vec(),
+        }
+    }
 }
 
 #[derive(Event, Debug)]
@@ -42,6 +115,3 @@ pub struct MoveRequest {
     pub move_from: GridCoords,
     pub move_to: GridCoords,
 }
-
-#[derive(Event)]
-pub struct RebuildNavmesh;
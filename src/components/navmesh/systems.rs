@@ -1,50 +1,13 @@
 use std::sync::{Arc, RwLock};
 
-use crate::components::INT_TILE_SIZE;
+use crate::components::{tile_to_world, INT_TILE_SIZE};
 
-use super::{components::*, MeshGrid, MoveRequest, NavmeshAnswerEvent, RebuildNavmesh};
-use bevy::{prelude::*, tasks::AsyncComputeTaskPool, utils::HashMap};
-use bevy_ecs_ldtk::prelude::*;
+use super::{
+    components::*, MeshGrid, MoveRequest, NavGrid, NavmeshAnswerEvent, WalkableState, FLOOR_COST,
+};
+use bevy::{prelude::*, tasks::AsyncComputeTaskPool};
 use futures_lite::future;
 
-pub fn update_navmesh(
-    changed_walkables: Query<
-        (&GridCoords, &WalkableState),
-        Or<(Added<WalkableState>, Changed<WalkableState>)>,
-    >,
-    navmesh_grid: ResMut<MeshGrid>,
-) {
-    for (coords, walkable) in &changed_walkables {
-        navmesh_grid
-            .grids_and_weights
-            .write()
-            .unwrap()
-            .insert(*coords, walkable.clone());
-    }
-}
-
-pub fn rebuild_navmesh(
-    mut reset_request: EventReader<RebuildNavmesh>,
-    walkables: Query<(&GridCoords, &WalkableState), With<WalkableState>>,
-    navmesh_grid: ResMut<MeshGrid>,
-) {
-    let mut reset = false;
-    for _ in &mut reset_request.read() {
-        reset = true;
-    }
-
-    if !reset {
-        return;
-    }
-
-    let mut grid = navmesh_grid.grids_and_weights.write().unwrap();
-
-    grid.clear();
-    for (coords, walkable) in &walkables {
-        grid.insert(*coords, walkable.clone());
-    }
-}
-
 pub fn listen_for_navmesh_requests(
     mut commands: Commands,
     mut request_listener: EventReader<MoveRequest>,
@@ -53,101 +16,86 @@ pub fn listen_for_navmesh_requests(
     if request_listener.is_empty() {
         return;
     }
-    let thread_pool = AsyncComputeTaskPool::get();
     for move_request in &mut request_listener.read() {
-        let move_request = move_request.clone();
-        let arc_grid = navmesh_grid.grids_and_weights.clone();
-        let task = thread_pool.spawn(async move { pathfind(move_request, arc_grid) });
-        commands.spawn(PathfindingTask(task));
+        commands.spawn(spawn_pathfinding_task(*move_request, &navmesh_grid));
     }
 }
 
+fn spawn_pathfinding_task(request: MoveRequest, navmesh_grid: &MeshGrid) -> PathfindingTask {
+    let arc_grid = navmesh_grid.grid.clone();
+    let task = AsyncComputeTaskPool::get().spawn(async move { pathfind(request, arc_grid) });
+
+    PathfindingTask { request, task }
+}
+
 pub fn poll_for_pathfinding_completion(
     mut commands: Commands,
+    navmesh_grid: Res<MeshGrid>,
     mut pathfinding_tasks: Query<(&mut PathfindingTask, Entity)>,
     mut event_sender: EventWriter<NavmeshAnswerEvent>,
 ) {
+    let generation = navmesh_grid.generation();
+
     for (mut pathfinding_task, entity) in &mut pathfinding_tasks {
-        if let Some(item) = future::block_on(future::poll_once(&mut pathfinding_task.0)) {
-            commands.entity(entity).despawn();
-            event_sender.send(item);
+        let Some((found_on, answer)) =
+            future::block_on(future::poll_once(&mut pathfinding_task.task))
+        else {
+            continue;
+        };
+
+        // Rooms changed while the path was being found, it could lead through tiles which are gone
+        if found_on != generation {
+            debug!("Navmesh changed during pathfinding, finding the path again");
+            *pathfinding_task = spawn_pathfinding_task(pathfinding_task.request, &navmesh_grid);
+            continue;
         }
+
+        commands.entity(entity).despawn();
+        event_sender.send(answer);
     }
 }
 
-/// A* implementation
-pub fn pathfind(
-    request: MoveRequest,
-    grid: Arc<RwLock<HashMap<GridCoords, WalkableState>>>,
-) -> NavmeshAnswerEvent {
-    use pathfinding::prelude::*;
-
+/// Pathfinding run on a task, also returning the generation of the navmesh the path was found on
+pub fn pathfind(request: MoveRequest, grid: Arc<RwLock<NavGrid>>) -> (u64, NavmeshAnswerEvent) {
     let Ok(grid) = grid.read() else {
-        return NavmeshAnswerEvent {
-            path: Err(()),
-            requesting_entity: request.requesting_entity,
-        };
+        return (
+            0,
+            NavmeshAnswerEvent {
+                path: Err(()),
+                requesting_entity: request.requesting_entity,
+            },
+        );
     };
 
-    let result = astar(
-        &request.move_from,
-        |&coord| {
-            let up = GridCoords::new(coord.x, coord.y + 1);
-            let right = GridCoords::new(coord.x + 1, coord.y);
-            let down = GridCoords::new(coord.x, coord.y - 1);
-            let left = GridCoords::new(coord.x - 1, coord.y);
-
-            let neighbors = [up, down, left, right]
-                .iter()
-                .filter(|&coord| {
-                    grid.contains_key(coord)
-                        && grid.get(coord).unwrap() != &WalkableState::NotWalkable
-                })
-                .map(|coord| (coord.clone(), 1))
-                .collect::<Vec<_>>();
-            neighbors
-        },
-        |&a| {
-            (Vec2::new(a.x as f32, a.y as f32)
-                - Vec2::new(request.move_to.x as f32, request.move_to.y as f32))
-            .length() as i32
+    (
+        grid.generation(),
+        NavmeshAnswerEvent {
+            requesting_entity: request.requesting_entity,
+            path: grid.find_path(request.move_from, request.move_to).ok_or(()),
         },
-        |&p| p == request.move_to,
-    );
+    )
+}
 
-    let Some((path, _)) = result else {
-        return NavmeshAnswerEvent {
-            path: Err(()),
-            requesting_entity: request.requesting_entity,
-        };
-    };
+pub fn debug_tiles(mut gizmos: Gizmos, navmesh_grid: Res<MeshGrid>, input: Res<Input<KeyCode>>) {
+    if !input.pressed(KeyCode::Insert) {
+        return;
+    }
 
-    return NavmeshAnswerEvent {
-        requesting_entity: request.requesting_entity,
-        path: Ok(path),
+    let Ok(grid) = navmesh_grid.grid.read() else {
+        return;
     };
-}
 
-pub fn debug_tiles(
-    mut gizmos: Gizmos,
-    tiles: Query<(&WalkableState, &GridCoords)>,
-    input: Res<Input<KeyCode>>,
-) {
-    if input.pressed(KeyCode::Insert) {
-        for (walkable, coords) in &tiles {
-            let color = match walkable {
-                WalkableState::NotWalkable => Color::RED,
-                WalkableState::Walkable => Color::GREEN,
-            };
-            gizmos.rect_2d(
-                Vec2::new(
-                    INT_TILE_SIZE * coords.x as f32 + INT_TILE_SIZE / 2.,
-                    INT_TILE_SIZE * coords.y as f32 + INT_TILE_SIZE / 2.,
-                ),
-                0.,
-                Vec2::new(INT_TILE_SIZE, INT_TILE_SIZE),
-                color,
-            );
-        }
+    for (coords, walkable) in grid.iter() {
+        let color = match walkable {
+            WalkableState::NotWalkable => Color::RED,
+            WalkableState::Walkable(FLOOR_COST) => Color::GREEN,
+            WalkableState::Walkable(_) => Color::YELLOW,
+        };
+        gizmos.rect_2d(
+            tile_to_world(coords),
+            0.,
+            Vec2::new(INT_TILE_SIZE, INT_TILE_SIZE),
+            color,
+        );
     }
 }
@@ -0,0 +1,341 @@
+use super::{LobbyConfig, NetworkEvent, NetworkedLobby};
+use crate::components::character::{CharacterType, SelectedCharacter};
+use crate::GameState;
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+use bevy_matchbox::prelude::*;
+
+/// Every player needs a character of their own, so there can't be more players than characters
+pub const MAX_PLAYERS: usize = CharacterType::ALL.len();
+
+/// Games are for two players or more, so the host only has a player count to pick once there are
+/// characters for three
+pub const PLAYER_COUNT_PICKABLE: bool = MAX_PLAYERS > 2;
+
+/// Who is in the lobby as this peer sees it, before the game starts
+#[derive(Resource, Default, Debug)]
+pub struct LobbyPeers {
+    local: Option<PeerId>,
+    peers: Vec<PeerId>,
+    ready: HashSet<PeerId>,
+    picks: HashMap<PeerId, CharacterType>,
+}
+
+impl LobbyPeers {
+    pub fn local(&self) -> Option<PeerId> {
+        self.local
+    }
+
+    /// Everyone in the lobby, this peer included
+    pub fn all(&self) -> impl Iterator<Item = PeerId> + '_ {
+        self.local.into_iter().chain(self.peers.iter().copied())
+    }
+
+    /// The host picks the player count and starts the game. Every peer agrees on who that is
+    /// without having to ask, as it is the lowest id.
+    pub fn host(&self) -> Option<PeerId> {
+        self.all().min()
+    }
+
+    pub fn is_host(&self) -> bool {
+        self.local.is_some() && self.local == self.host()
+    }
+
+    pub fn is_ready(&self, peer: PeerId) -> bool {
+        self.ready.contains(&peer)
+    }
+
+    /// Who has a character. Peers can pick the same one at the same time, then the lowest id
+    /// keeps it, which every peer works out the same way once it has heard from both.
+    pub fn owner(&self, character: CharacterType) -> Option<PeerId> {
+        self.all()
+            .filter(|peer| self.picks.get(peer) == Some(&character))
+            .min()
+    }
+
+    /// The character a peer picked, if nobody else has it
+    pub fn character_of(&self, peer: PeerId) -> Option<CharacterType> {
+        self.picks
+            .get(&peer)
+            .copied()
+            .filter(|character| self.owner(*character) == Some(peer))
+    }
+
+    /// Who plays which character when the game starts. Players who haven't picked get one of the
+    /// characters nobody has, those left over once they run out sit the game out.
+    fn assign_characters(&self) -> Vec<(PeerId, CharacterType)> {
+        let mut players = self.all().collect::<Vec<_>>();
+        players.sort();
+
+        let mut free = CharacterType::ALL
+            .into_iter()
+            .filter(|character| self.owner(*character).is_none());
+
+        players
+            .into_iter()
+            .filter_map(|peer| Some((peer, self.character_of(peer).or_else(|| free.next())?)))
+            .collect()
+    }
+
+    /// The lobby the game starts with. Peers can still disagree about picks which crossed on the
+    /// way, so only the host builds it and everyone else plays the characters it sends.
+    fn build_lobby(&self) -> Option<NetworkedLobby> {
+        self.is_host()
+            .then(|| NetworkedLobby::new(self.assign_characters()))
+    }
+}
+
+/// What the player asked for on the lobby screen
+#[derive(Event, Debug, Clone, Copy)]
+pub enum LobbyAction {
+    ToggleReady,
+    /// Picks the character to play, as long as nobody else has it
+    PickCharacter(CharacterType),
+    /// Adds to or takes from the player count, only the host can
+    ChangePlayerCount(i32),
+    /// Starts with whoever is in the lobby, only the host can
+    ForceStart,
+}
+
+/// Messages which came in during the lobby but are meant for the game, kept until it starts
+#[derive(Resource, Default)]
+pub struct DeferredMessages(pub Vec<(PeerId, Box<[u8]>)>);
+
+fn send_to(socket: &mut MatchboxSocket<SingleChannel>, peers: &[PeerId], event: &NetworkEvent) {
+    let Ok(data) = bincode::serialize(event) else {
+        return;
+    };
+    let boxed = data.into_boxed_slice();
+
+    for peer in peers {
+        socket.send(boxed.clone(), *peer);
+    }
+}
+
+pub fn update_lobby_peers(
+    socket: Option<ResMut<MatchboxSocket<SingleChannel>>>,
+    mut lobby_peers: ResMut<LobbyPeers>,
+    lobby_config: Res<LobbyConfig>,
+) {
+    let Some(mut socket) = socket else {
+        return;
+    };
+
+    if socket.get_channel(0).is_err() {
+        return;
+    }
+
+    let changes = socket.update_peers();
+
+    if lobby_peers.local.is_none() {
+        lobby_peers.local = socket.id();
+    }
+
+    for (peer, state) in changes {
+        match state {
+            PeerState::Connected => {
+                info!("{:?} joined the lobby", peer);
+                lobby_peers.peers.push(peer);
+
+                // Newcomers have to be told what everyone else already knows
+                let local_ready = lobby_peers
+                    .local
+                    .is_some_and(|local| lobby_peers.is_ready(local));
+                send_to(&mut socket, &[peer], &NetworkEvent::Ready(local_ready));
+
+                if let Some(character) = lobby_peers
+                    .local
+                    .and_then(|local| lobby_peers.picks.get(&local))
+                {
+                    send_to(
+                        &mut socket,
+                        &[peer],
+                        &NetworkEvent::PickCharacter(*character),
+                    );
+                }
+
+                if lobby_peers.is_host() {
+                    send_to(
+                        &mut socket,
+                        &[peer],
+                        &NetworkEvent::LobbySize {
+                            requested_players: lobby_config.requested_players,
+                        },
+                    );
+                }
+            }
+            PeerState::Disconnected => {
+                info!("{:?} left the lobby", peer);
+                lobby_peers.peers.retain(|p| *p != peer);
+                lobby_peers.ready.remove(&peer);
+                lobby_peers.picks.remove(&peer);
+            }
+        }
+    }
+}
+
+pub fn receive_lobby_messages(
+    mut commands: Commands,
+    socket: Option<ResMut<MatchboxSocket<SingleChannel>>>,
+    mut lobby_peers: ResMut<LobbyPeers>,
+    mut lobby_config: ResMut<LobbyConfig>,
+    mut deferred: ResMut<DeferredMessages>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    let Some(mut socket) = socket else {
+        return;
+    };
+
+    if socket.get_channel(0).is_err() {
+        return;
+    }
+
+    for (peer, data) in socket.receive() {
+        let Ok(network_event) = bincode::deserialize::<NetworkEvent>(&data) else {
+            continue;
+        };
+
+        let from_host = lobby_peers.host() == Some(peer);
+
+        match network_event {
+            NetworkEvent::Ready(true) => {
+                lobby_peers.ready.insert(peer);
+            }
+            NetworkEvent::Ready(false) => {
+                lobby_peers.ready.remove(&peer);
+            }
+            NetworkEvent::PickCharacter(character) => {
+                lobby_peers.picks.insert(peer, character);
+            }
+            NetworkEvent::LobbySize { requested_players } if from_host => {
+                lobby_config.requested_players = requested_players;
+            }
+            NetworkEvent::StartGame { lobby } if from_host => {
+                start_game(&mut commands, &mut game_state, lobby_peers.local, lobby);
+            }
+            NetworkEvent::LobbySize { .. } | NetworkEvent::StartGame { .. } => {
+                warn!(
+                    "Ignoring lobby settings from {:?}, who isn't the host",
+                    peer
+                );
+            }
+            _ => deferred.0.push((peer, data)),
+        }
+    }
+}
+
+pub fn handle_lobby_actions(
+    socket: Option<ResMut<MatchboxSocket<SingleChannel>>>,
+    mut lobby_actions: EventReader<LobbyAction>,
+    mut lobby_peers: ResMut<LobbyPeers>,
+    mut lobby_config: ResMut<LobbyConfig>,
+) {
+    let Some(mut socket) = socket else {
+        return;
+    };
+
+    let Some(local) = lobby_peers.local else {
+        return;
+    };
+
+    let peers = lobby_peers.peers.clone();
+
+    for action in &mut lobby_actions.read() {
+        match *action {
+            LobbyAction::PickCharacter(character) => {
+                if lobby_peers
+                    .owner(character)
+                    .is_some_and(|owner| owner != local)
+                {
+                    continue;
+                }
+
+                lobby_peers.picks.insert(local, character);
+                send_to(&mut socket, &peers, &NetworkEvent::PickCharacter(character));
+            }
+            LobbyAction::ToggleReady => {
+                // Nobody can be ready without a character to play
+                let ready =
+                    !lobby_peers.is_ready(local) && lobby_peers.character_of(local).is_some();
+                if ready {
+                    lobby_peers.ready.insert(local);
+                } else {
+                    lobby_peers.ready.remove(&local);
+                }
+
+                send_to(&mut socket, &peers, &NetworkEvent::Ready(ready));
+            }
+            LobbyAction::ChangePlayerCount(change) if lobby_peers.is_host() => {
+                // Counted without the local player, who is always there
+                let requested_players = (lobby_config.requested_players as i32 + change)
+                    .clamp(1, (MAX_PLAYERS as i32 - 1).max(1))
+                    as usize;
+                lobby_config.requested_players = requested_players;
+
+                send_to(
+                    &mut socket,
+                    &peers,
+                    &NetworkEvent::LobbySize { requested_players },
+                );
+            }
+            LobbyAction::ForceStart if lobby_peers.is_host() => {
+                lobby_config.force_start = true;
+            }
+            LobbyAction::ChangePlayerCount(_) | LobbyAction::ForceStart => {}
+        }
+    }
+}
+
+/// The host starts the game once the lobby is full and everyone in it is ready, or right away
+/// when forced to, and tells everyone else who is playing
+pub fn start_when_ready(
+    mut commands: Commands,
+    socket: Option<ResMut<MatchboxSocket<SingleChannel>>>,
+    lobby_peers: Res<LobbyPeers>,
+    lobby_config: Res<LobbyConfig>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    let Some(mut socket) = socket else {
+        return;
+    };
+
+    let full = lobby_peers.peers.len() == lobby_config.requested_players;
+    let all_ready = lobby_peers
+        .all()
+        .all(|peer| lobby_peers.is_ready(peer) && lobby_peers.character_of(peer).is_some());
+
+    if !lobby_config.force_start && !(full && all_ready) {
+        return;
+    }
+
+    let Some(lobby) = lobby_peers.build_lobby() else {
+        return;
+    };
+    send_to(
+        &mut socket,
+        &lobby_peers.peers,
+        &NetworkEvent::StartGame {
+            lobby: lobby.clone(),
+        },
+    );
+
+    start_game(&mut commands, &mut game_state, lobby_peers.local, lobby);
+}
+
+fn start_game(
+    commands: &mut Commands,
+    game_state: &mut NextState<GameState>,
+    local: Option<PeerId>,
+    lobby: NetworkedLobby,
+) {
+    let Some(local_character) = local.and_then(|local| lobby.character_of(local)) else {
+        warn!("The game started without a character left for this player");
+        return;
+    };
+
+    info!("Starting the game in turn order {:?}", lobby.turn_order);
+
+    commands.insert_resource(SelectedCharacter(local_character));
+    commands.insert_resource(lobby);
+    game_state.set(GameState::InitialSpawn);
+}
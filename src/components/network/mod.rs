@@ -0,0 +1,616 @@
+mod lobby;
+
+pub use lobby::{LobbyAction, LobbyPeers, MAX_PLAYERS, PLAYER_COUNT_PICKABLE};
+
+use super::{
+    character::{
+        spawn_network_player, CharacterTraits, CharacterType, CharacterWalk, Dead, NetworkPlayer,
+        NetworkTransform, Player,
+    },
+    room::{CharacterTeleportEvent, RemoteRoomEvent, RoomLevel, RoomPlacedEvent, RoomRotation},
+    turn::{EndTurnEvent, TurnState},
+    MeshGrid, NavmeshAnswerEvent,
+};
+use crate::{settings::Settings, GameState};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_ecs_ldtk::GridCoords;
+use bevy_matchbox::prelude::*;
+use lobby::*;
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use the_haunted_mansion::signaling::{start_signaling_server, SIGNALING_PORT};
+
+#[derive(States, Default, Debug, Hash, Eq, PartialEq, Clone)]
+enum NetworkState {
+    #[default]
+    WaitingForPlayers,
+    Playing,
+    Disconnected,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+enum NetworkEvent {
+    PlayerPathing(VecDeque<(i32, i32)>),
+    Hello {
+        initial_position: Vec3,
+        traits: CharacterTraits,
+    },
+    TraitsFor(CharacterTraits),
+    /// A room the sender drew, or turned while they could still place it
+    RoomSpawned {
+        room: String,
+        location: (i32, i32),
+        level: RoomLevel,
+        rotation: RoomRotation,
+    },
+    Teleport {
+        position: Vec3,
+    },
+    /// Ends the sender's turn, `turn` counts the turns ended before it
+    EndTurn {
+        turn: u32,
+    },
+    /// Whether the sender is ready for the game to start
+    Ready(bool),
+    /// How many other players the host is waiting for
+    LobbySize {
+        requested_players: usize,
+    },
+    /// The character the sender wants to play
+    PickCharacter(CharacterType),
+    /// Sent by the host when the game starts, with everyone who is playing, as whom and in
+    /// which order
+    StartGame {
+        lobby: NetworkedLobby,
+    },
+}
+
+pub struct NetworkPlugin;
+
+impl Plugin for NetworkPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_state::<NetworkState>()
+            .add_systems(
+                Update,
+                (
+                    init_networked_players
+                        .run_if(resource_exists::<CharacterWalk>())
+                        .run_if(resource_exists::<NetworkedLobby>())
+                        .run_if(in_state(NetworkState::WaitingForPlayers)),
+                    listen_for_start_multiplayer,
+                ),
+            )
+            .add_systems(
+                Update,
+                (
+                    update_lobby_peers,
+                    receive_lobby_messages,
+                    handle_lobby_actions,
+                    start_when_ready,
+                )
+                    .chain()
+                    .run_if(in_state(GameState::Lobby)),
+            )
+            .add_systems(
+                Update,
+                recieve_remote_state.run_if(in_state(NetworkState::Playing)),
+            )
+            .add_systems(
+                Update,
+                broadcast_player_pathfinding.run_if(in_state(NetworkState::Playing)),
+            )
+            .add_systems(
+                Update,
+                broadcast_player_teleport.run_if(in_state(NetworkState::Playing)),
+            )
+            .add_systems(
+                Update,
+                broadcast_room_placement.run_if(in_state(NetworkState::Playing)),
+            )
+            .add_systems(
+                Update,
+                (broadcast_end_turn, order_turns_by_lobby)
+                    .chain()
+                    .run_if(in_state(NetworkState::Playing)),
+            )
+            .add_event::<StartMultiplayer>()
+            .add_event::<LobbyAction>()
+            .init_resource::<LobbyConfig>()
+            .init_resource::<LobbyPeers>()
+            .init_resource::<DeferredMessages>();
+    }
+}
+
+#[derive(Resource)]
+pub struct LobbyConfig {
+    /// How many players besides this one the game waits for, picked by the host
+    pub requested_players: usize,
+    /// The host started without waiting for the lobby to fill up or be ready
+    pub force_start: bool,
+}
+
+/// The players of a networked game. It is built by the host when the game starts and sent to
+/// everyone else, so every peer has the same turn order and seed.
+#[derive(Resource, Serialize, Deserialize, Debug, Clone)]
+pub struct NetworkedLobby {
+    turn_index: usize,
+    turn_order: Vec<PeerId>,
+    characters: HashMap<CharacterType, PeerId>,
+    /// Turns ended since the game started, so a late or repeated end of turn can be told apart
+    turns_taken: u32,
+    /// Shared by every peer, so they all shuffle the decks and roll the dice the same way
+    seed: u64,
+}
+
+impl NetworkedLobby {
+    /// A lobby for the given players and the characters they play, in a random turn order
+    fn new(characters: Vec<(PeerId, CharacterType)>) -> Self {
+        let mut turn_order = characters.iter().map(|(peer, _)| *peer).collect::<Vec<_>>();
+        turn_order.shuffle(&mut rand::thread_rng());
+
+        Self {
+            turn_index: 0,
+            turn_order,
+            characters: characters
+                .into_iter()
+                .map(|(peer, character)| (character, peer))
+                .collect(),
+            turns_taken: 0,
+            seed: rand::random(),
+        }
+    }
+
+    pub fn character_of(&self, peer: PeerId) -> Option<CharacterType> {
+        self.characters
+            .iter()
+            .find(|(_, player)| **player == peer)
+            .map(|(character, _)| *character)
+    }
+
+    /// The peer whose turn it is, the only one whose moves are accepted
+    pub fn active_peer(&self) -> Option<PeerId> {
+        self.turn_order.get(self.turn_index).copied()
+    }
+
+    fn end_turn(&mut self) {
+        self.turn_index = (self.turn_index + 1) % self.turn_order.len().max(1);
+        self.turns_taken += 1;
+    }
+}
+
+/// The things a game shuffles or rolls, each with its own random numbers
+#[derive(Debug, Clone, Copy)]
+pub enum RandomStream {
+    CardDecks,
+    Dice,
+    RoomDeck,
+}
+
+/// The seed for one of the game's random streams. Peers in a networked game share them, a game
+/// on its own gets a fresh one.
+pub fn shared_seed(lobby: Option<&NetworkedLobby>, stream: RandomStream) -> u64 {
+    lobby
+        .map(|lobby| lobby.seed.wrapping_add(stream as u64))
+        .unwrap_or_else(rand::random)
+}
+
+#[derive(Event)]
+pub struct StartMultiplayer {
+    /// Run the signaling server in this game and connect to it, instead of the configured one
+    pub host: bool,
+}
+
+impl Default for LobbyConfig {
+    fn default() -> Self {
+        Self {
+            force_start: false,
+            requested_players: 1,
+        }
+    }
+}
+
+fn listen_for_start_multiplayer(
+    mut commands: Commands,
+    mut evt: EventReader<StartMultiplayer>,
+    maybe_started: Option<Res<MatchboxSocket<SingleChannel>>>,
+    settings: Res<Settings>,
+) {
+    for start in &mut evt.read() {
+        if maybe_started.is_some() {
+            return;
+        }
+
+        let room_url = if start.host {
+            commands.insert_resource(start_signaling_server(SIGNALING_PORT));
+            settings.hosted_room_url(SIGNALING_PORT)
+        } else {
+            settings.room_url()
+        };
+
+        info!("Connecting to matchmaking server at {}", room_url);
+        commands.insert_resource(MatchboxSocket::new_reliable(room_url));
+        return;
+    }
+}
+
+fn init_networked_players(
+    mut commands: Commands,
+    asset: Res<CharacterWalk>,
+    local_player: Query<(Entity, &CharacterTraits, &Transform), With<Player>>,
+    socket: Option<ResMut<MatchboxSocket<SingleChannel>>>,
+    lobby: Res<NetworkedLobby>,
+    mut network_state: ResMut<NextState<NetworkState>>,
+) {
+    let Some(mut socket) = socket else {
+        return;
+    };
+
+    if socket.get_channel(0).is_err() {
+        return; // multiplayer session has already started
+    }
+
+    let Some(my_local_id) = socket.id() else {
+        return;
+    };
+
+    let Ok((local_player_entity, character_traits, transform)) = local_player.get_single() else {
+        return;
+    };
+
+    info!("Lobby started. Starting match");
+
+    let peers = lobby.turn_order.clone();
+
+    let hello = bincode::serialize(&NetworkEvent::Hello {
+        initial_position: transform.translation,
+        traits: character_traits.clone(),
+    })
+    .ok()
+    .map(|d| d.into_boxed_slice());
+
+    for peer in peers {
+        if peer == my_local_id {
+            let network_player = NetworkPlayer { player_id: peer };
+            commands
+                .entity(local_player_entity)
+                .insert((network_player, NetworkTransform::default()));
+            continue;
+        }
+        let character = lobby.character_of(peer).unwrap_or_default();
+        spawn_network_player(&mut commands, &asset, peer, character);
+
+        if let Some(hello) = &hello {
+            socket.send(hello.clone(), peer);
+        }
+    }
+
+    network_state.set(NetworkState::Playing);
+}
+
+fn recieve_remote_state(
+    mut players: Query<
+        (
+            Entity,
+            &mut NetworkTransform,
+            &NetworkPlayer,
+            &mut CharacterTraits,
+            &mut Transform,
+        ),
+        Without<Player>,
+    >,
+    socket: Option<ResMut<MatchboxSocket<SingleChannel>>>,
+    mut deferred: ResMut<DeferredMessages>,
+    mut lobby: Option<ResMut<NetworkedLobby>>,
+    mut end_turn_event: EventWriter<EndTurnEvent>,
+    mut remote_room_event: EventWriter<RemoteRoomEvent>,
+) {
+    let Some(mut socket) = socket else {
+        return;
+    };
+
+    let messages = deferred
+        .0
+        .drain(..)
+        .chain(socket.receive())
+        .collect::<Vec<_>>();
+
+    for (peer, data) in messages {
+        let Some((player_entity, mut net_trans, _, mut traits, mut transform)) = players
+            .iter_mut()
+            .find(|(_, _, ref player, _, _)| player.player_id == peer)
+        else {
+            continue;
+        };
+
+        let Ok(network_event) = bincode::deserialize::<NetworkEvent>(&data) else {
+            continue;
+        };
+
+        // Only the peer whose turn it is can move or end the turn
+        let is_active = lobby
+            .as_ref()
+            .map_or(true, |lobby| lobby.active_peer() == Some(peer));
+
+        match network_event {
+            NetworkEvent::PlayerPathing(vecs) if is_active => {
+                net_trans.move_path = vecs
+                    .into_iter()
+                    .map(|(x, y)| GridCoords::new(x, y))
+                    .collect()
+            }
+            NetworkEvent::Hello {
+                initial_position,
+                traits: network_traits,
+            } => {
+                transform.translation = initial_position;
+                *traits = network_traits;
+            }
+            NetworkEvent::TraitsFor(recv_traits) => {
+                *traits = recv_traits;
+            }
+            NetworkEvent::Teleport { position } if is_active => {
+                transform.translation = position;
+                net_trans.move_path.clear();
+                net_trans.move_to = None;
+            }
+            NetworkEvent::EndTurn { turn } if is_active => {
+                if let Some(lobby) = lobby.as_mut() {
+                    if turn != lobby.turns_taken {
+                        warn!(
+                            "Ignoring the end of turn {} from {:?}, it is turn {}",
+                            turn, peer, lobby.turns_taken
+                        );
+                        continue;
+                    }
+
+                    lobby.end_turn();
+                }
+
+                end_turn_event.send(EndTurnEvent {
+                    character_entity: player_entity,
+                });
+            }
+            NetworkEvent::RoomSpawned {
+                room,
+                location: (x, y),
+                level,
+                rotation,
+            } if is_active => {
+                remote_room_event.send(RemoteRoomEvent {
+                    room,
+                    location: GridCoords::new(x, y),
+                    level,
+                    rotation,
+                });
+            }
+            event @ (NetworkEvent::PlayerPathing(_)
+            | NetworkEvent::RoomSpawned { .. }
+            | NetworkEvent::Teleport { .. }
+            | NetworkEvent::EndTurn { .. }) => {
+                warn!("Ignoring {:?} from {:?}, it isn't their turn", event, peer);
+            }
+            // Left over from the lobby
+            event @ (NetworkEvent::Ready(_)
+            | NetworkEvent::PickCharacter(_)
+            | NetworkEvent::LobbySize { .. }
+            | NetworkEvent::StartGame { .. }) => {
+                warn!("Ignoring {:?} from {:?}, the game has started", event, peer);
+            }
+        }
+    }
+}
+
+fn broadcast_player_pathfinding(
+    socket: Option<ResMut<MatchboxSocket<SingleChannel>>>,
+    turn_state: Res<TurnState>,
+    navmesh_grid: Res<MeshGrid>,
+    mut pathfinding_event: EventReader<NavmeshAnswerEvent>,
+) {
+    let Some(mut socket) = socket else {
+        return;
+    };
+
+    let Some(self_id) = socket.id() else {
+        return;
+    };
+
+    let peers = socket
+        .connected_peers()
+        .filter(|p| p != &self_id)
+        .collect::<Vec<_>>();
+
+    for NavmeshAnswerEvent { path, .. } in &mut pathfinding_event.read() {
+        let mut path = path.clone().ok().unwrap_or(Vec::new());
+        // Peers only walk as far as the player does
+        navmesh_grid.truncate_path(&mut path, turn_state.movement_left());
+
+        let Ok(data_to_send) = bincode::serialize(&NetworkEvent::PlayerPathing(
+            navmesh_grid
+                .smooth_path(&path)
+                .into_iter()
+                .map(|i| (i.x, i.y))
+                .collect(),
+        )) else {
+            continue;
+        };
+
+        let boxed = data_to_send.into_boxed_slice();
+
+        for peer in &peers {
+            socket.send(boxed.clone(), *peer);
+        }
+    }
+}
+
+fn broadcast_player_teleport(
+    socket: Option<ResMut<MatchboxSocket<SingleChannel>>>,
+    player: Query<Entity, With<Player>>,
+    mut teleport_event: EventReader<CharacterTeleportEvent>,
+) {
+    let Some(mut socket) = socket else {
+        return;
+    };
+
+    let Ok(player_entity) = player.get_single() else {
+        return;
+    };
+
+    let peers = socket.connected_peers().collect::<Vec<_>>();
+
+    for CharacterTeleportEvent {
+        character_entity,
+        position,
+    } in &mut teleport_event.read()
+    {
+        if *character_entity != player_entity {
+            continue;
+        }
+
+        let Ok(data_to_send) = bincode::serialize(&NetworkEvent::Teleport {
+            position: *position,
+        }) else {
+            continue;
+        };
+
+        let boxed = data_to_send.into_boxed_slice();
+
+        for peer in &peers {
+            socket.send(boxed.clone(), *peer);
+        }
+    }
+}
+
+/// Tells everyone else about the rooms this player draws and turns
+fn broadcast_room_placement(
+    socket: Option<ResMut<MatchboxSocket<SingleChannel>>>,
+    mut room_placed_event: EventReader<RoomPlacedEvent>,
+) {
+    let Some(mut socket) = socket else {
+        return;
+    };
+
+    let peers = socket.connected_peers().collect::<Vec<_>>();
+
+    for RoomPlacedEvent {
+        room,
+        location,
+        level,
+        rotation,
+    } in &mut room_placed_event.read()
+    {
+        let Ok(data_to_send) = bincode::serialize(&NetworkEvent::RoomSpawned {
+            room: room.clone(),
+            location: (location.x, location.y),
+            level: *level,
+            rotation: *rotation,
+        }) else {
+            continue;
+        };
+
+        let boxed = data_to_send.into_boxed_slice();
+
+        for peer in &peers {
+            socket.send(boxed.clone(), *peer);
+        }
+    }
+}
+
+fn broadcast_end_turn(
+    socket: Option<ResMut<MatchboxSocket<SingleChannel>>>,
+    lobby: Option<ResMut<NetworkedLobby>>,
+    player: Query<Entity, With<Player>>,
+    mut end_turn_event: EventReader<EndTurnEvent>,
+) {
+    let Some(mut socket) = socket else {
+        return;
+    };
+
+    let Some(mut lobby) = lobby else {
+        return;
+    };
+
+    let Ok(player_entity) = player.get_single() else {
+        return;
+    };
+
+    let Some(self_id) = socket.id() else {
+        return;
+    };
+
+    let peers = socket
+        .connected_peers()
+        .filter(|p| p != &self_id)
+        .collect::<Vec<_>>();
+
+    for EndTurnEvent { character_entity } in &mut end_turn_event.read() {
+        if *character_entity != player_entity || lobby.active_peer() != Some(self_id) {
+            continue;
+        }
+
+        let Ok(data_to_send) = bincode::serialize(&NetworkEvent::EndTurn {
+            turn: lobby.turns_taken,
+        }) else {
+            continue;
+        };
+
+        lobby.end_turn();
+
+        let boxed = data_to_send.into_boxed_slice();
+
+        for peer in &peers {
+            socket.send(boxed.clone(), *peer);
+        }
+    }
+}
+
+/// Turns follow the lobby's order of peers, instead of the order their characters spawned in,
+/// and the lobby decides whose turn it is
+fn order_turns_by_lobby(
+    lobby: Option<ResMut<NetworkedLobby>>,
+    mut turn_state: ResMut<TurnState>,
+    players: Query<(Entity, &NetworkPlayer), Without<Dead>>,
+) {
+    let Some(mut lobby) = lobby else {
+        return;
+    };
+
+    let entity_of = |peer: &PeerId| {
+        players
+            .iter()
+            .find(|(_, player)| player.player_id == *peer)
+            .map(|(entity, _)| entity)
+    };
+
+    let order = lobby
+        .turn_order
+        .iter()
+        .filter_map(entity_of)
+        .collect::<Vec<_>>();
+
+    if order != turn_state.order() {
+        turn_state.set_order(order);
+    }
+
+    // The dead don't take turns, every peer skips them the same way
+    if lobby
+        .turn_order
+        .iter()
+        .any(|peer| entity_of(peer).is_some())
+    {
+        while lobby
+            .active_peer()
+            .and_then(|peer| entity_of(&peer))
+            .is_none()
+        {
+            lobby.turn_index = (lobby.turn_index + 1) % lobby.turn_order.len();
+        }
+    }
+
+    if let Some(active) = lobby.active_peer().and_then(|peer| entity_of(&peer)) {
+        if !turn_state.is_active(active) {
+            turn_state.set_active(active);
+        }
+    }
+}
@@ -1,8 +1,13 @@
-use super::RoomLevel;
-use crate::components::card::CardType;
+use super::{DoorLocation, RoomLevel, ROOM_TILE_COUNT};
+use crate::components::card::CardSymbol;
 use bevy::{prelude::*, utils::HashSet};
-use bevy_ecs_ldtk::prelude::*;
+use bevy_ecs_ldtk::{
+    ldtk::{FieldValue, Level},
+    prelude::*,
+};
 use derivative::Derivative;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::FRAC_PI_2;
 
 pub mod ldtk {
     use bevy::prelude::*;
@@ -18,27 +23,18 @@ pub mod ldtk {
     #[derive(Component, Default)]
     pub struct RoomBound;
 
+    /// A sensor over a doorway, `size` is its width and height before the room is rotated
     #[derive(Component)]
-    pub struct RoomBoundComponent;
+    pub struct RoomBoundComponent {
+        pub size: Vec2,
+    }
 
     #[derive(LdtkIntCell, Bundle, Default)]
     pub struct RoomBoundBundle {
         room_bound: RoomBound,
     }
-
-    #[derive(Component, Default)]
-    pub struct Walkable;
-
-    #[derive(Component)]
-    pub struct WalkableComponent;
-
-    #[derive(LdtkIntCell, Bundle, Default)]
-    pub struct WalkableBundle {
-        walkable: Walkable,
-    }
 }
 
-
 #[derive(Component, Default, Debug)]
 pub struct Wall;
 
@@ -53,6 +49,39 @@ pub struct RoomBundle {
     pub name: Name,
     pub room: Room,
     pub location: GridCoords,
+    pub rotation: RoomRotation,
+    pub level: RoomLevel,
+}
+
+/// The number of 90° counter-clockwise turns applied to a room
+#[derive(
+    Component, Default, Debug, Clone, Copy, PartialEq, Eq, Reflect, Serialize, Deserialize,
+)]
+pub struct RoomRotation(pub u8);
+
+impl RoomRotation {
+    pub fn quat(&self) -> Quat {
+        Quat::from_rotation_z(FRAC_PI_2 * (self.0 % 4) as f32)
+    }
+
+    /// How many turns it takes to get from `self` to `other`
+    pub fn turns_to(&self, other: RoomRotation) -> u8 {
+        (other.0 % 4 + 4 - self.0 % 4) % 4
+    }
+
+    /// Rotates a door bitmask, where up = 0b1000 and left = 0b0001
+    pub fn rotate_doors(&self, door_connections: u8) -> u8 {
+        (0..self.0 % 4).fold(door_connections, |mask, _| {
+            ((mask << 1) | (mask >> 3)) & 0b1111
+        })
+    }
+
+    /// Rotates a tile position which is local to the room, where 0, 0 is the bottom left tile
+    pub fn rotate_tile(&self, coords: GridCoords) -> GridCoords {
+        (0..self.0 % 4).fold(coords, |coords, _| {
+            GridCoords::new(ROOM_TILE_COUNT - 1 - coords.y, coords.x)
+        })
+    }
 }
 
 #[derive(Derivative, Component, Debug, Clone, Reflect)]
@@ -66,7 +95,11 @@ pub struct Room {
     pub allowed_copies: u8,
     #[derivative(Hash = "ignore")]
     #[derivative(PartialEq = "ignore")]
-    pub card: Option<CardType>,
+    pub card: Option<CardSymbol>,
+    /// The floor which can be reached by taking the stairs in this room
+    #[derivative(Hash = "ignore")]
+    #[derivative(PartialEq = "ignore")]
+    pub stairs_to: Option<RoomLevel>,
 
     /// ```rust
     /// // up    = 0b1000
@@ -76,3 +109,80 @@ pub struct Room {
     /// ```
     pub door_connections: u8,
 }
+
+impl TryFrom<&Level> for Room {
+    type Error = String;
+
+    /// Reads a room out of the custom fields of an LDtk level
+    fn try_from(level: &Level) -> Result<Self, Self::Error> {
+        let field = |identifier: &str| {
+            level
+                .field_instances
+                .iter()
+                .find(|field| field.identifier == identifier)
+                .map(|field| &field.value)
+                .ok_or_else(|| format!("missing the '{}' field", identifier))
+        };
+
+        let FieldValue::Int(Some(allowed_copies)) = field("AllowedCopies")? else {
+            return Err("'AllowedCopies' must be set".into());
+        };
+        let FieldValue::Int(Some(door_connections)) = field("DoorConnections")? else {
+            return Err("'DoorConnections' must be set".into());
+        };
+        let FieldValue::Enums(floors) = field("Floors")? else {
+            return Err("'Floors' must be a list of floors".into());
+        };
+        let FieldValue::Enum(card) = field("CardSymbol")? else {
+            return Err("'CardSymbol' must be a card symbol".into());
+        };
+        let FieldValue::Enum(stairs_to) = field("StairsTo")? else {
+            return Err("'StairsTo' must be a floor".into());
+        };
+
+        let room_level = floors
+            .iter()
+            .flatten()
+            .map(|floor| RoomLevel::from_ldtk(floor))
+            .collect::<Option<HashSet<_>>>()
+            .ok_or_else(|| format!("unknown floor in {:?}", floors))?;
+
+        let card = match card {
+            Some(card) => Some(
+                CardSymbol::from_ldtk(card).ok_or_else(|| format!("unknown card '{}'", card))?,
+            ),
+            None => None,
+        };
+
+        let stairs_to = match stairs_to {
+            Some(floor) => Some(
+                RoomLevel::from_ldtk(floor).ok_or_else(|| format!("unknown floor '{}'", floor))?,
+            ),
+            None => None,
+        };
+
+        Ok(Self {
+            name: level.identifier.clone(),
+            iid: level.iid.clone(),
+            room_level,
+            allowed_copies: *allowed_copies as u8,
+            card,
+            stairs_to,
+            door_connections: (*door_connections & 0b1111) as u8,
+        })
+    }
+}
+
+impl Room {
+    pub fn rotated_door_connections(&self, rotation: RoomRotation) -> u8 {
+        rotation.rotate_doors(self.door_connections)
+    }
+
+    /// All rotations of this room which put a door on the given side
+    pub fn rotations_with_door(&self, door: DoorLocation) -> Vec<RoomRotation> {
+        (0..4)
+            .map(RoomRotation)
+            .filter(|rotation| self.rotated_door_connections(*rotation) & door as u8 != 0)
+            .collect()
+    }
+}
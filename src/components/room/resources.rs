@@ -1,8 +1,11 @@
 use bevy::{prelude::*, utils::HashMap};
 use bevy_asset_loader::prelude::*;
-use bevy_ecs_ldtk::assets::LdtkProject;
+use bevy_ecs_ldtk::{assets::LdtkProject, GridCoords};
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
 
-use super::Room;
+use super::{Room, RoomRotation, FLOOR_ROOM_SPACING, ROOM_SIZE};
 
 #[derive(AssetCollection, Resource)]
 pub struct RoomAssets {
@@ -11,6 +14,7 @@ pub struct RoomAssets {
 }
 
 #[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum DoorLocation {
     Up = 0b1000,
     Right = 0b0100,
@@ -18,6 +22,54 @@ pub enum DoorLocation {
     Left = 0b0001,
 }
 
+impl DoorLocation {
+    /// Picks the door a point belongs to, where the point is relative to the center of the room
+    pub fn from_offset(offset: Vec2) -> Self {
+        if offset.x.abs() > offset.y.abs() {
+            if offset.x > 0. {
+                Self::Right
+            } else {
+                Self::Left
+            }
+        } else if offset.y > 0. {
+            Self::Up
+        } else {
+            Self::Down
+        }
+    }
+
+    pub fn opposite(&self) -> Self {
+        match self {
+            Self::Up => Self::Down,
+            Self::Right => Self::Left,
+            Self::Down => Self::Up,
+            Self::Left => Self::Right,
+        }
+    }
+
+    /// The room grid offset of the room on the other side of this door
+    pub fn offset(&self) -> (i32, i32) {
+        match self {
+            Self::Up => (0, 1),
+            Self::Right => (1, 0),
+            Self::Down => (0, -1),
+            Self::Left => (-1, 0),
+        }
+    }
+}
+
+/// Every room which can be placed in the mansion, read from the levels of the LDtk project
+#[derive(Resource, Default)]
+pub struct RoomCatalog {
+    pub rooms: Vec<Room>,
+}
+
+impl RoomCatalog {
+    pub fn find(&self, name: &str) -> Option<&Room> {
+        self.rooms.iter().find(|room| room.name == name)
+    }
+}
+
 #[derive(Resource, Clone, Default)]
 pub struct RoomCounter {
     pub rooms: HashMap<Room, u8>,
@@ -27,14 +79,158 @@ pub struct RoomCounter {
     pub spawnable_upper: HashMap<Room, u8>,
 }
 
-#[derive(Hash, Eq, PartialEq, Debug, Clone, Reflect)]
-#[allow(dead_code)]
+impl RoomCounter {
+    pub fn spawnable_mut(&mut self, level: &RoomLevel) -> &mut HashMap<Room, u8> {
+        match level {
+            RoomLevel::Basement => &mut self.spawnable_basement,
+            RoomLevel::Ground => &mut self.spawnable_ground,
+            RoomLevel::Upper => &mut self.spawnable_upper,
+        }
+    }
+
+    pub fn copies_placed(&self, room: &Room) -> u8 {
+        self.rooms.get(room).copied().unwrap_or(0)
+    }
+}
+
+#[derive(Component, Hash, Eq, PartialEq, Debug, Clone, Copy, Reflect, Serialize, Deserialize)]
 pub enum RoomLevel {
     Basement,
     Ground,
     Upper,
 }
 
+impl RoomLevel {
+    /// Parse the `RoomFloor` enum used in the LDtk project
+    pub fn from_ldtk(value: &str) -> Option<Self> {
+        match value {
+            "Basement" => Some(Self::Basement),
+            "Ground" => Some(Self::Ground),
+            "Upper" => Some(Self::Upper),
+            _ => None,
+        }
+    }
+
+    pub fn z_index(&self) -> i8 {
+        match self {
+            Self::Basement => -1,
+            Self::Ground => 0,
+            Self::Upper => 1,
+        }
+    }
+
+    /// Floors are laid out apart from each other in world space so that their colliders and
+    /// navmesh tiles never overlap. This is the offset, in rooms, of this floor's grid.
+    pub fn room_offset(&self) -> GridCoords {
+        GridCoords::new(0, self.z_index() as i32 * FLOOR_ROOM_SPACING)
+    }
+
+    /// Get the floor a world position belongs to
+    pub fn from_world_position(position: Vec2) -> Self {
+        let floor_height = FLOOR_ROOM_SPACING as f32 * ROOM_SIZE;
+
+        match (position.y / floor_height).round() as i32 {
+            i32::MIN..=-1 => Self::Basement,
+            0 => Self::Ground,
+            _ => Self::Upper,
+        }
+    }
+}
+
+/// The shuffled stack of rooms drawn while exploring. The order only depends on the seed and
+/// the spawnable rooms, so peers sharing a seed will draw the same rooms.
+#[derive(Resource, Debug, Clone)]
+pub struct RoomDeck {
+    rooms: VecDeque<Room>,
+}
+
+impl RoomDeck {
+    pub fn new(seed: u64, counter: &RoomCounter) -> Self {
+        // A room can be in the pool of more than one floor, but it only has one set of copies
+        let mut copies: HashMap<&Room, u8> = HashMap::new();
+        for (room, &remaining) in counter
+            .spawnable_basement
+            .iter()
+            .chain(counter.spawnable_ground.iter())
+            .chain(counter.spawnable_upper.iter())
+        {
+            let left_to_place = room
+                .allowed_copies
+                .saturating_sub(counter.copies_placed(room));
+            let count = copies.entry(room).or_default();
+            *count = (*count).max(remaining.min(left_to_place));
+        }
+
+        // HashMap iteration order is random, sort before shuffling so the seed alone decides
+        let mut copies = copies.into_iter().collect::<Vec<_>>();
+        copies.sort_by(|(a, _), (b, _)| a.name.cmp(&b.name));
+
+        let mut rooms = copies
+            .into_iter()
+            .flat_map(|(room, count)| std::iter::repeat(room.clone()).take(count as usize))
+            .collect::<Vec<_>>();
+        rooms.shuffle(&mut StdRng::seed_from_u64(seed));
+
+        Self {
+            rooms: rooms.into(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.rooms.len()
+    }
+
+    /// Every room left in the deck which can be placed on the given floor, in draw order
+    pub fn for_floor<'a>(&'a self, level: &'a RoomLevel) -> impl Iterator<Item = &'a Room> {
+        self.rooms
+            .iter()
+            .filter(move |room| room.room_level.contains(level))
+    }
+
+    /// Takes out the first copy of a room, returns false when it wasn't in the deck
+    pub fn take(&mut self, room: &Room) -> bool {
+        let Some(index) = self.rooms.iter().position(|r| r == room) else {
+            return false;
+        };
+
+        self.rooms.remove(index);
+        true
+    }
+}
+
+/// A freshly drawn room which the placing player can still rotate before walking into it
+#[derive(Resource)]
+pub struct RoomPlacement {
+    pub room_entity: Entity,
+    pub rotations: Vec<RoomRotation>,
+    pub index: usize,
+}
+
+/// A room this player drew or turned, so the other players can place it the same way
+#[derive(Event, Debug, Clone)]
+pub struct RoomPlacedEvent {
+    pub room: String,
+    pub location: GridCoords,
+    pub level: RoomLevel,
+    pub rotation: RoomRotation,
+}
+
+/// A room another player drew or turned, to be placed the same way here
+#[derive(Event, Debug, Clone)]
+pub struct RemoteRoomEvent {
+    pub room: String,
+    pub location: GridCoords,
+    pub level: RoomLevel,
+    pub rotation: RoomRotation,
+}
+
+/// Turns a placed room counter-clockwise, along with the navmesh tiles already spawned for it
+#[derive(Event)]
+pub struct RotateRoomEvent {
+    pub room_entity: Entity,
+    pub turns: u8,
+}
+
 #[derive(Event, Hash, PartialEq, Eq)]
 pub struct RoomBoundsHitEvent {
     pub character_entity: Entity,
@@ -42,10 +238,18 @@ pub struct RoomBoundsHitEvent {
     pub room: Room,
 }
 
+/// Sent when a character is moved somewhere without walking there, such as taking the stairs
+#[derive(Event)]
+pub struct CharacterTeleportEvent {
+    pub character_entity: Entity,
+    pub position: Vec3,
+}
+
 #[repr(i32)]
 #[allow(dead_code)]
 pub enum LayerMask {
     NonWalkable = 1,
     RoomBound = 2,
     Walkable = 3,
+    Difficult = 4,
 }
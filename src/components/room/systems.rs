@@ -1,21 +1,56 @@
 use super::components::{ldtk::*, *};
-use super::LDTK_ROOMS;
 use super::ROOM_SIZE;
 use super::{resources::*, INT_TILE_SIZE};
-use crate::components::character::Player;
-use crate::components::navmesh::NavmeshParent;
-use crate::components::{NavmeshBundle, NavmeshTileBundle};
+use crate::components::character::{CharacterInput, CharacterProps, CurrentRoom, Player};
+use crate::components::{
+    shared_seed, MeshGrid, NavChunk, NetworkedLobby, RandomStream, RoomLinks, TurnState,
+    WalkableState, DIFFICULT_COST, DOORWAY_COST, FLOOR_COST, ROOM_CROSSING_COST,
+};
+use crate::events::GameEvent;
 use crate::prelude::*;
-use bevy::math::Vec3A;
 use bevy::prelude::*;
 use bevy::render::primitives::Aabb;
 use bevy::sprite::collide_aabb::{collide, Collision};
 use bevy::utils::{HashMap, HashSet};
 use bevy_ecs_ldtk::prelude::*;
 use bevy_rapier2d::prelude::*;
+use leafwing_input_manager::prelude::*;
 
-fn room_location_to_position(location: (i32, i32)) -> Vec2 {
-    Vec2::new(location.0 as f32 * ROOM_SIZE, location.1 as f32 * ROOM_SIZE)
+/// The room grid coordinates of a room once its floor has been taken into account
+pub fn world_room_coords(location: &GridCoords, level: &RoomLevel) -> GridCoords {
+    *location + level.room_offset()
+}
+
+pub fn room_location_to_position(location: &GridCoords, level: &RoomLevel) -> Vec2 {
+    let location = world_room_coords(location, level);
+    Vec2::new(location.x as f32 * ROOM_SIZE, location.y as f32 * ROOM_SIZE)
+}
+
+/// The tile a world position is on. Tiles span from their bottom left corner, so positions
+/// below zero, such as the basement, round down too.
+pub fn world_to_tile(position: Vec2) -> GridCoords {
+    GridCoords::new(
+        (position.x / INT_TILE_SIZE).floor() as i32,
+        (position.y / INT_TILE_SIZE).floor() as i32,
+    )
+}
+
+/// The world position of the center of a tile
+pub fn tile_to_world(tile: GridCoords) -> Vec2 {
+    (Vec2::new(tile.x as f32, tile.y as f32) + 0.5) * INT_TILE_SIZE
+}
+
+/// The transform of a room entity, rotated around the center of the room
+fn room_transform(location: &GridCoords, level: &RoomLevel, rotation: RoomRotation) -> Transform {
+    let world_pos = room_location_to_position(location, level).extend(-1.);
+    let center = Vec3::new(ROOM_SIZE / 2., ROOM_SIZE / 2., 0.);
+    let quat = rotation.quat();
+
+    Transform {
+        translation: world_pos + center - quat * center,
+        rotation: quat,
+        ..default()
+    }
 }
 
 pub fn spawn_room(
@@ -24,27 +59,31 @@ pub fn spawn_room(
     counter: &mut ResMut<RoomCounter>,
     put_at: GridCoords,
     room: Room,
-    z_index: i8,
-) {
+    rotation: RoomRotation,
+    level: RoomLevel,
+) -> Entity {
     let ldtk_handle = &room_assets.ldtk_asset;
-    let world_pos = room_location_to_position((put_at.x, put_at.y));
-    commands.spawn((
-        RoomBundle {
-            ldtk: LdtkWorldBundle {
-                ldtk_handle: ldtk_handle.clone(),
-                level_set: LevelSet::from_iids([room.iid.to_owned()]),
-                transform: Transform::from_xyz(world_pos.x, world_pos.y, -1.),
-                ..default()
+    let room_entity = commands
+        .spawn((
+            RoomBundle {
+                ldtk: LdtkWorldBundle {
+                    ldtk_handle: ldtk_handle.clone(),
+                    level_set: LevelSet::from_iids([room.iid.to_owned()]),
+                    transform: room_transform(&put_at, &level, rotation),
+                    ..default()
+                },
+                name: Name::new(room.name.to_owned()),
+                room: room.to_owned(),
+                location: put_at,
+                rotation,
+                level,
             },
-            name: Name::new(room.name.to_owned()),
-            room: room.to_owned(),
-            location: put_at,
-        },
-        Aabb {
-            half_extents: Vec3::new(ROOM_SIZE / 2., ROOM_SIZE / 2., 0.).into(),
-            center: Vec3::new(ROOM_SIZE / 2., ROOM_SIZE / 2., 0.).into(),
-        },
-    ));
+            Aabb {
+                half_extents: Vec3::new(ROOM_SIZE / 2., ROOM_SIZE / 2., 0.).into(),
+                center: Vec3::new(ROOM_SIZE / 2., ROOM_SIZE / 2., 0.).into(),
+            },
+        ))
+        .id();
 
     if counter.rooms.contains_key(&room) {
         *counter.rooms.get_mut(&room).unwrap() += 1;
@@ -54,50 +93,169 @@ pub fn spawn_room(
 
     counter
         .filled_tiles
-        .insert((put_at.x, put_at.y, z_index), room.to_owned());
+        .insert((put_at.x, put_at.y, level.z_index()), room.to_owned());
+
+    room_entity
 }
 
-pub fn setup_first_rooms(
+pub fn build_room_catalog(
     mut commands: Commands,
     room_assets: Res<RoomAssets>,
-    mut room_counter: ResMut<RoomCounter>,
+    ldtk_projects: Res<Assets<LdtkProject>>,
 ) {
-    let entryway = LDTK_ROOMS.iter().find(|room| room.name == "Entryway");
-    let entryway_location = GridCoords::new(0, 0);
-    let hallway = LDTK_ROOMS.iter().find(|room| room.name == "Hallway-2x0y");
-    let hallway_location = GridCoords::new(1, 0);
-    let hallway_4way = LDTK_ROOMS.iter().find(|room| room.name == "Hallway-2x2y");
-    let hallway_4way_location = GridCoords::new(2, 0);
-
-    let (Some(entryway), Some(hallway), Some(hallway_4way)) = (entryway, hallway, hallway_4way)
-    else {
-        panic!("Cannot find the first rooms: 'entryway' -- 'hallway-2x0y'");
+    let Some(project) = ldtk_projects.get(&room_assets.ldtk_asset) else {
+        panic!("The LDtk project has not finished loading");
     };
 
-    spawn_room(
-        &mut commands,
-        &room_assets,
-        &mut room_counter,
-        entryway_location,
-        entryway.clone(),
-        0,
-    );
-    spawn_room(
-        &mut commands,
-        &room_assets,
-        &mut room_counter,
-        hallway_location,
-        hallway.clone(),
-        0,
-    );
-    spawn_room(
-        &mut commands,
-        &room_assets,
-        &mut room_counter,
-        hallway_4way_location,
-        hallway_4way.clone(),
-        0,
+    let rooms = project
+        .json_data()
+        .levels
+        .iter()
+        .filter_map(|level| match Room::try_from(level) {
+            Ok(room) => Some(room),
+            Err(err) => {
+                error!("Skipping level '{}': {}", level.identifier, err);
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+
+    info!("Loaded {} rooms from the LDtk project", rooms.len());
+
+    commands.insert_resource(RoomCatalog { rooms });
+}
+
+pub fn fill_spawnable_rooms(room_catalog: Res<RoomCatalog>, mut room_counter: ResMut<RoomCounter>) {
+    for room in &room_catalog.rooms {
+        for level in &room.room_level {
+            room_counter
+                .spawnable_mut(level)
+                .insert(room.clone(), room.allowed_copies);
+        }
+    }
+}
+
+pub fn shuffle_room_deck(
+    mut commands: Commands,
+    room_counter: Res<RoomCounter>,
+    lobby: Option<Res<NetworkedLobby>>,
+) {
+    let seed = shared_seed(lobby.as_deref(), RandomStream::RoomDeck);
+    let deck = RoomDeck::new(seed, &room_counter);
+
+    info!(
+        "Shuffled {} rooms into the deck with seed {}",
+        deck.len(),
+        seed
     );
+
+    commands.insert_resource(deck);
+}
+
+/// Draws the next room on the given floor which can still be placed and can be rotated to put
+/// a door on the `required_door` side. Rooms which don't fit stay where they are, so the deck
+/// only ever loses the room drawn and other peers can take the same one out of theirs.
+fn draw_room(
+    deck: &mut RoomDeck,
+    counter: &RoomCounter,
+    level: &RoomLevel,
+    required_door: DoorLocation,
+) -> Option<Room> {
+    let room = deck
+        .for_floor(level)
+        .find(|room| {
+            counter.copies_placed(room) < room.allowed_copies
+                && !room.rotations_with_door(required_door).is_empty()
+        })
+        .cloned()?;
+
+    deck.take(&room);
+    Some(room)
+}
+
+pub fn setup_first_rooms(
+    mut commands: Commands,
+    room_assets: Res<RoomAssets>,
+    room_catalog: Res<RoomCatalog>,
+    mut room_counter: ResMut<RoomCounter>,
+) {
+    let first_rooms = [
+        ("Entryway", GridCoords::new(0, 0), RoomLevel::Ground),
+        ("Hallway_2x0y", GridCoords::new(1, 0), RoomLevel::Ground),
+        ("GrandStaircase", GridCoords::new(2, 0), RoomLevel::Ground),
+        ("UpperLanding", GridCoords::new(0, 0), RoomLevel::Upper),
+        (
+            "BasementLanding",
+            GridCoords::new(0, 0),
+            RoomLevel::Basement,
+        ),
+    ];
+
+    for (name, location, level) in first_rooms {
+        let Some(room) = room_catalog.find(name) else {
+            panic!("Cannot find the first room: '{}'", name);
+        };
+
+        spawn_room(
+            &mut commands,
+            &room_assets,
+            &mut room_counter,
+            location,
+            room.clone(),
+            RoomRotation::default(),
+            level,
+        );
+    }
+}
+
+/// Covers a room's tiles with a few rectangles instead of one per tile, each given as its bottom
+/// left tile and its width and height in tiles. Runs along a row are found first, then grown
+/// upwards for as long as the rows above have the same run.
+fn merge_tiles(tiles: &HashSet<GridCoords>) -> Vec<(GridCoords, IVec2)> {
+    let mut sorted = tiles.iter().copied().collect::<Vec<_>>();
+    sorted.sort_by_key(|coords| (coords.y, coords.x));
+
+    let mut left = tiles.clone();
+    let mut rects = Vec::new();
+    for corner in sorted {
+        if !left.contains(&corner) {
+            continue;
+        }
+
+        let mut width = 1;
+        while left.contains(&GridCoords::new(corner.x + width, corner.y)) {
+            width += 1;
+        }
+
+        let mut height = 1;
+        while (0..width).all(|x| left.contains(&GridCoords::new(corner.x + x, corner.y + height))) {
+            height += 1;
+        }
+
+        for x in 0..width {
+            for y in 0..height {
+                left.remove(&GridCoords::new(corner.x + x, corner.y + y));
+            }
+        }
+
+        rects.push((corner, IVec2::new(width, height)));
+    }
+
+    rects
+}
+
+/// A collider over a rectangle of tiles, placed relative to the room
+fn tile_rect_collider(corner: GridCoords, size: IVec2) -> (Collider, TransformBundle) {
+    let half_size = size.as_vec2() * INT_TILE_SIZE / 2.;
+
+    (
+        Collider::cuboid(half_size.x, half_size.y),
+        TransformBundle::from_transform(Transform::from_xyz(
+            corner.x as f32 * INT_TILE_SIZE + half_size.x,
+            corner.y as f32 * INT_TILE_SIZE + half_size.y,
+            0.,
+        )),
+    )
 }
 
 pub fn spawn_wall_colliders(
@@ -105,13 +263,12 @@ pub fn spawn_wall_colliders(
     non_walkable_query: Query<(&GridCoords, &Parent), Added<NonWalkable>>,
     parent_query: Query<&Parent, Without<NonWalkable>>,
     grandparent_query: Query<&Parent, With<LevelIid>>,
-    room_query: Query<(Entity, &GridCoords), With<Room>>,
-    navmesh: Query<Entity, With<NavmeshParent>>,
+    room_query: Query<Entity, With<Room>>,
 ) {
     let mut level_to_non_walkable_locations: HashMap<Entity, HashSet<GridCoords>> = HashMap::new();
 
     non_walkable_query.for_each(|(&grid_coords, parent)| {
-        let Ok((room_entity, _)) = parent_query
+        let Ok(room_entity) = parent_query
             .get(parent.get())
             .and_then(|grandparent| grandparent_query.get(grandparent.get()))
             .and_then(|parent| room_query.get(parent.get()))
@@ -125,15 +282,15 @@ pub fn spawn_wall_colliders(
             .insert(grid_coords);
     });
 
-    for (entity, room_coords) in &room_query {
+    for entity in &room_query {
         let Some(grid_coords) = level_to_non_walkable_locations.get(&entity) else {
             continue;
         };
 
-        for coord in grid_coords {
+        for (corner, size) in merge_tiles(grid_coords) {
             let collider = commands
                 .spawn((
-                    Collider::cuboid(INT_TILE_SIZE / 2., INT_TILE_SIZE / 2.),
+                    tile_rect_collider(corner, size),
                     CollisionGroups {
                         memberships: Group::GROUP_1,
                         ..default()
@@ -141,50 +298,10 @@ pub fn spawn_wall_colliders(
                     RigidBody::Fixed,
                     ActiveEvents::COLLISION_EVENTS,
                     Name::new("Non-Walkable Collider"),
-                    TransformBundle {
-                        local: Transform::from_xyz(
-                            coord.x as f32 * INT_TILE_SIZE + INT_TILE_SIZE / 2.,
-                            coord.y as f32 * INT_TILE_SIZE + INT_TILE_SIZE / 2.,
-                            0.,
-                        ),
-                        ..Default::default()
-                    },
                 ))
                 .id();
 
             commands.entity(entity).add_child(collider);
-
-            let Ok(navmesh_entity) = navmesh.get_single() else {
-                continue;
-            };
-
-            let transform = Vec2::new(
-                (coord.x as f32 * INT_TILE_SIZE) + (ROOM_SIZE * (room_coords.x as f32)),
-                (coord.y as f32 * INT_TILE_SIZE) + (ROOM_SIZE * (room_coords.y as f32)),
-            );
-
-            let navmesh_tile = commands
-                .spawn((
-                    NavmeshTileBundle {
-                        grid_coord: GridCoords {
-                            x: (transform.x / INT_TILE_SIZE) as i32,
-                            y: (transform.y / INT_TILE_SIZE) as i32,
-                        },
-                        walkable: super::WalkableState::NotWalkable,
-                        transform: TransformBundle {
-                            local: Transform::from_xyz(transform.x, transform.y, 1.),
-                            ..default()
-                        },
-                    },
-                    Name::new("NotWalkable"),
-                    Aabb {
-                        half_extents: Vec3A::new(INT_TILE_SIZE / 2., INT_TILE_SIZE / 2., 0.5),
-                        ..Default::default()
-                    },
-                ))
-                .id();
-
-            commands.entity(navmesh_entity).add_child(navmesh_tile);
         }
     }
 }
@@ -194,13 +311,12 @@ pub fn spawn_room_bounds(
     non_walkable_query: Query<(&GridCoords, &Parent), Added<RoomBound>>,
     parent_query: Query<&Parent, Without<RoomBound>>,
     grandparent_query: Query<&Parent, With<LevelIid>>,
-    room_query: Query<(Entity, &GridCoords), With<Room>>,
-    navmesh: Query<Entity, With<NavmeshParent>>,
+    room_query: Query<Entity, With<Room>>,
 ) {
     let mut level_to_room_bound_locations: HashMap<Entity, HashSet<GridCoords>> = HashMap::new();
 
     non_walkable_query.for_each(|(&grid_coords, parent)| {
-        let Ok((room_entity, _)) = parent_query
+        let Ok(room_entity) = parent_query
             .get(parent.get())
             .and_then(|grandparent| grandparent_query.get(grandparent.get()))
             .and_then(|parent| room_query.get(parent.get()))
@@ -214,15 +330,15 @@ pub fn spawn_room_bounds(
             .insert(grid_coords);
     });
 
-    for (entity, room_coords) in &room_query {
+    for entity in &room_query {
         let Some(grid_coords) = level_to_room_bound_locations.get(&entity) else {
             continue;
         };
 
-        for coord in grid_coords {
+        for (corner, size) in merge_tiles(grid_coords) {
             let collider = commands
                 .spawn((
-                    Collider::cuboid(INT_TILE_SIZE / 2., INT_TILE_SIZE / 2.),
+                    tile_rect_collider(corner, size),
                     CollisionGroups {
                         memberships: Group::GROUP_2,
                         ..default()
@@ -231,165 +347,520 @@ pub fn spawn_room_bounds(
                     RigidBody::Fixed,
                     Sensor,
                     Name::new("Room Bound Sensor"),
-                    TransformBundle {
-                        local: Transform::from_xyz(
-                            coord.x as f32 * INT_TILE_SIZE + INT_TILE_SIZE / 2.,
-                            coord.y as f32 * INT_TILE_SIZE + INT_TILE_SIZE / 2.,
-                            0.,
-                        ),
-                        ..Default::default()
+                    RoomBoundComponent {
+                        size: size.as_vec2() * INT_TILE_SIZE,
                     },
-                    RoomBoundComponent,
                 ))
                 .id();
 
             commands.entity(entity).add_child(collider);
+        }
+    }
+}
 
-            let Ok(navmesh_entity) = navmesh.get_single() else {
+pub fn check_room_entry_or_exit(
+    mut commands: Commands,
+    mut character_query: Query<
+        (
+            Entity,
+            &GlobalTransform,
+            Option<&mut CurrentRoom>,
+            Has<Player>,
+        ),
+        With<CharacterProps>,
+    >,
+    room_query: Query<
+        (
+            &Room,
+            Entity,
+            &Aabb,
+            &GlobalTransform,
+            &GridCoords,
+            &RoomLevel,
+        ),
+        With<Room>,
+    >,
+    mut room_event: EventWriter<RoomBoundsHitEvent>,
+    mut game_event: EventWriter<GameEvent>,
+) {
+    for (character_entity, character_transform, current_room, is_player) in &mut character_query {
+        let entered = room_query
+            .iter()
+            .find(|(_, _, room_aabb, _, room_coords, level)| {
+                let tx = room_location_to_position(room_coords, level).extend(0.)
+                    + Vec3::new(ROOM_SIZE / 2., ROOM_SIZE / 2., 0.);
+
+                matches!(
+                    collide(
+                        tx,
+                        room_aabb.half_extents.truncate() * 2.,
+                        character_transform.translation(),
+                        Vec2::ONE,
+                    ),
+                    Some(Collision::Inside)
+                )
+            });
+
+        // Standing on a room edge, keep the last room until the character is fully inside another
+        let Some((room, room_entity, room_aabb, room_transform, _, _)) = entered else {
+            continue;
+        };
+
+        if let Some(current_room) = current_room.as_ref() {
+            if current_room.room_entity == room_entity {
                 continue;
-            };
+            }
 
-            let transform = Vec2::new(
-                (coord.x as f32 * INT_TILE_SIZE) + (ROOM_SIZE * (room_coords.x as f32)),
-                (coord.y as f32 * INT_TILE_SIZE) + (ROOM_SIZE * (room_coords.y as f32)),
-            );
+            game_event.send(GameEvent::RoomLeave {
+                character_entity,
+                room_entity: current_room.room_entity,
+                room: current_room.room.clone(),
+            });
+        }
 
-            let navmesh_tile = commands
-                .spawn((
-                    NavmeshTileBundle {
-                        grid_coord: GridCoords {
-                            x: (transform.x / INT_TILE_SIZE) as i32,
-                            y: (transform.y / INT_TILE_SIZE) as i32,
-                        },
-                        walkable: super::WalkableState::Walkable,
-                        transform: TransformBundle {
-                            local: Transform::from_xyz(transform.x, transform.y, 1.),
-                            ..default()
-                        },
-                    },
-                    Name::new("Walkable"),
-                    Aabb {
-                        half_extents: Vec3A::new(INT_TILE_SIZE / 2., INT_TILE_SIZE / 2., 0.5),
-                        ..Default::default()
-                    },
-                ))
-                .id();
+        game_event.send(GameEvent::RoomEnter {
+            character_entity,
+            room_entity,
+            room: room.clone(),
+        });
 
-            commands.entity(navmesh_entity).add_child(navmesh_tile);
+        let new_room = CurrentRoom {
+            room_entity,
+            room: room.clone(),
+        };
+
+        match current_room {
+            Some(mut current_room) => *current_room = new_room,
+            None => {
+                commands.entity(character_entity).insert(new_room);
+            }
+        }
+
+        if !is_player {
+            continue;
         }
+
+        let player_pos_in_room =
+            character_transform_to_pos_in_room(character_transform, room_transform, room_aabb);
+
+        room_event.send(RoomBoundsHitEvent {
+            character_entity,
+            room_entity,
+            room: room.clone(),
+        })
     }
 }
 
-pub fn spawn_walkable_navtiles(
+pub fn explore_unexplored_doors(
     mut commands: Commands,
-    walkable_query: Query<(&GridCoords, &Parent), Added<Walkable>>,
-    parent_query: Query<&Parent, Without<Walkable>>,
-    grandparent_query: Query<&Parent, With<LevelIid>>,
-    room_query: Query<(Entity, &GridCoords), With<Room>>,
-    navmesh: Query<Entity, With<NavmeshParent>>,
+    room_assets: Res<RoomAssets>,
+    mut room_counter: ResMut<RoomCounter>,
+    mut room_deck: ResMut<RoomDeck>,
+    turn_state: Res<TurnState>,
+    player_query: Query<(Entity, &GlobalTransform), With<Player>>,
+    room_bounds: Query<(&GlobalTransform, &RoomBoundComponent, &Parent)>,
+    room_query: Query<(&Room, &GridCoords, &RoomRotation, &RoomLevel)>,
+    mut room_placed_event: EventWriter<RoomPlacedEvent>,
 ) {
-    let mut level_to_room_bound_locations: HashMap<Entity, HashSet<GridCoords>> = HashMap::new();
+    let Ok((player_entity, player_transform)) = player_query.get_single() else {
+        return;
+    };
 
-    walkable_query.for_each(|(&grid_coords, parent)| {
-        let Ok((room_entity, _)) = parent_query
-            .get(parent.get())
-            .and_then(|grandparent| grandparent_query.get(grandparent.get()))
-            .and_then(|parent| room_query.get(parent.get()))
-        else {
-            return;
+    // Other peers only take rooms from whoever's turn it is
+    if !turn_state.is_active(player_entity) {
+        return;
+    }
+
+    for (bound_transform, bound, parent) in &room_bounds {
+        // The sensor turns with its room, so a doorway along one side is along another now
+        let (_, bound_rotation, _) = bound_transform.to_scale_rotation_translation();
+        let bound_size = (bound_rotation * bound.size.extend(0.)).truncate().abs();
+
+        if collide(
+            bound_transform.translation(),
+            bound_size,
+            player_transform.translation(),
+            Vec2::ONE,
+        )
+        .is_none()
+        {
+            continue;
+        }
+
+        let Ok((room, room_coords, rotation, level)) = room_query.get(parent.get()) else {
+            continue;
         };
 
-        level_to_room_bound_locations
-            .entry(room_entity)
-            .or_default()
-            .insert(grid_coords);
-    });
+        let room_center =
+            room_location_to_position(room_coords, level) + Vec2::splat(ROOM_SIZE / 2.);
+        let door =
+            DoorLocation::from_offset(bound_transform.translation().truncate() - room_center);
 
-    for (entity, room_coords) in &room_query {
-        let Some(grid_coords) = level_to_room_bound_locations.get(&entity) else {
+        if room.rotated_door_connections(*rotation) & door as u8 == 0 {
+            continue;
+        }
+
+        let (offset_x, offset_y) = door.offset();
+        let next_location = GridCoords::new(room_coords.x + offset_x, room_coords.y + offset_y);
+
+        if room_counter.filled_tiles.contains_key(&(
+            next_location.x,
+            next_location.y,
+            level.z_index(),
+        )) {
+            continue;
+        }
+
+        let Some(next_room) = draw_room(&mut room_deck, &room_counter, level, door.opposite())
+        else {
             continue;
         };
 
-        for coord in grid_coords {
-            let Ok(navmesh_entity) = navmesh.get_single() else {
-                continue;
-            };
+        if let Some(remaining) = room_counter.spawnable_mut(level).get_mut(&next_room) {
+            *remaining -= 1;
+        }
+
+        info!("Drew {} at {:?}", next_room.name, next_location);
+
+        let rotations = next_room.rotations_with_door(door.opposite());
+        room_placed_event.send(RoomPlacedEvent {
+            room: next_room.name.clone(),
+            location: next_location,
+            level: *level,
+            rotation: rotations[0],
+        });
+
+        let room_entity = spawn_room(
+            &mut commands,
+            &room_assets,
+            &mut room_counter,
+            next_location,
+            next_room,
+            rotations[0],
+            *level,
+        );
+
+        commands.insert_resource(RoomPlacement {
+            room_entity,
+            rotations,
+            index: 0,
+        });
+    }
+}
+
+pub fn rotate_placed_room(
+    placement: Option<ResMut<RoomPlacement>>,
+    input_query: Query<&ActionState<CharacterInput>, With<Player>>,
+    room_query: Query<(&Room, &RoomRotation, &GridCoords, &RoomLevel)>,
+    mut rotate_room_event: EventWriter<RotateRoomEvent>,
+    mut room_placed_event: EventWriter<RoomPlacedEvent>,
+) {
+    let Some(mut placement) = placement else {
+        return;
+    };
+    let Ok(input) = input_query.get_single() else {
+        return;
+    };
+
+    if !input.just_pressed(CharacterInput::RotateRoom) || placement.rotations.len() < 2 {
+        return;
+    }
+
+    let Ok((room, rotation, location, level)) = room_query.get(placement.room_entity) else {
+        return;
+    };
+
+    placement.index = (placement.index + 1) % placement.rotations.len();
+    let next_rotation = placement.rotations[placement.index];
 
-            let transform = Vec2::new(
-                (coord.x as f32 * INT_TILE_SIZE) + (ROOM_SIZE * (room_coords.x as f32)),
-                (coord.y as f32 * INT_TILE_SIZE) + (ROOM_SIZE * (room_coords.y as f32)),
+    rotate_room_event.send(RotateRoomEvent {
+        room_entity: placement.room_entity,
+        turns: rotation.turns_to(next_rotation),
+    });
+    room_placed_event.send(RoomPlacedEvent {
+        room: room.name.clone(),
+        location: *location,
+        level: *level,
+        rotation: next_rotation,
+    });
+}
+
+/// Places and turns the rooms other players drew, so every peer builds the same mansion
+pub fn place_remote_rooms(
+    mut commands: Commands,
+    room_assets: Res<RoomAssets>,
+    room_catalog: Res<RoomCatalog>,
+    mut room_counter: ResMut<RoomCounter>,
+    mut room_deck: ResMut<RoomDeck>,
+    room_query: Query<(Entity, &GridCoords, &RoomLevel, &RoomRotation), With<Room>>,
+    mut remote_room_event: EventReader<RemoteRoomEvent>,
+    mut rotate_room_event: EventWriter<RotateRoomEvent>,
+) {
+    // A room can be drawn and turned in the same frame, only the last word on each spot counts
+    let mut latest: HashMap<(i32, i32, i8), &RemoteRoomEvent> = HashMap::new();
+    for event in &mut remote_room_event.read() {
+        latest.insert(
+            (event.location.x, event.location.y, event.level.z_index()),
+            event,
+        );
+    }
+
+    for event in latest.into_values() {
+        let placed = room_query
+            .iter()
+            .find(|(_, location, level, _)| **location == event.location && **level == event.level);
+
+        if let Some((room_entity, _, _, rotation)) = placed {
+            let turns = rotation.turns_to(event.rotation);
+            if turns > 0 {
+                rotate_room_event.send(RotateRoomEvent { room_entity, turns });
+            }
+            continue;
+        }
+
+        let Some(room) = room_catalog.find(&event.room).cloned() else {
+            warn!(
+                "Another player placed {}, which isn't a known room",
+                event.room
             );
+            continue;
+        };
 
-            let navmesh_tile = commands
-                .spawn((
-                    NavmeshTileBundle {
-                        grid_coord: GridCoords {
-                            x: (transform.x / INT_TILE_SIZE) as i32,
-                            y: (transform.y / INT_TILE_SIZE) as i32,
-                        },
-                        walkable: super::WalkableState::Walkable,
-                        transform: TransformBundle {
-                            local: Transform::from_xyz(transform.x, transform.y, 1.),
-                            ..default()
-                        },
-                    },
-                    Aabb {
-                        half_extents: Vec3A::new(INT_TILE_SIZE / 2., INT_TILE_SIZE / 2., 0.5),
-                        ..Default::default()
-                    },
-                    Name::new("Walkable"),
-                ))
-                .id();
+        if !room_deck.take(&room) {
+            warn!(
+                "Another player drew {}, which isn't in the deck here",
+                room.name
+            );
+        }
 
-            commands.entity(navmesh_entity).add_child(navmesh_tile);
+        if let Some(remaining) = room_counter.spawnable_mut(&event.level).get_mut(&room) {
+            *remaining = remaining.saturating_sub(1);
         }
+
+        info!("Another player drew {} at {:?}", room.name, event.location);
+
+        spawn_room(
+            &mut commands,
+            &room_assets,
+            &mut room_counter,
+            event.location,
+            room,
+            event.rotation,
+            event.level,
+        );
     }
 }
 
-pub fn check_room_entry_or_exit(
-    mut player_query: Query<(Entity, &GlobalTransform, &mut Player), With<Player>>,
-    room_query: Query<(&Room, Entity, &Aabb, &GlobalTransform), With<Room>>,
-    mut room_event: EventWriter<RoomBoundsHitEvent>,
+pub fn rotate_room(
+    mut rotate_room_event: EventReader<RotateRoomEvent>,
+    mut room_query: Query<(&mut RoomRotation, &mut Transform, &GridCoords, &RoomLevel), With<Room>>,
 ) {
-    let Ok((player_entity, player_global_transform, mut player)) = player_query.get_single_mut()
-    else {
+    for event in &mut rotate_room_event.read() {
+        let Ok((mut rotation, mut transform, room_coords, level)) =
+            room_query.get_mut(event.room_entity)
+        else {
+            continue;
+        };
+
+        let next_rotation = RoomRotation((rotation.0 + event.turns % 4) % 4);
+
+        *rotation = next_rotation;
+        *transform = room_transform(room_coords, level, next_rotation);
+    }
+}
+
+/// Builds the navmesh of a room straight from its IntGrid layer, whenever it is placed, moved or turned
+pub fn build_room_navmesh(
+    room_assets: Res<RoomAssets>,
+    ldtk_projects: Res<Assets<LdtkProject>>,
+    mut navmesh_grid: ResMut<MeshGrid>,
+    room_query: Query<
+        (Entity, &Room, &GridCoords, &RoomRotation, &RoomLevel),
+        Or<(
+            Changed<RoomRotation>,
+            Changed<GridCoords>,
+            Changed<RoomLevel>,
+        )>,
+    >,
+) {
+    let Some(project) = ldtk_projects.get(&room_assets.ldtk_asset) else {
         return;
     };
 
-    for (room, room_entity, room_aabb, room_transform) in &room_query {
-        let tx = room_transform.translation() + Vec3::new(ROOM_SIZE / 2., ROOM_SIZE / 2., 0.);
+    for (room_entity, room, room_coords, rotation, level) in &room_query {
+        let Some(ldtk_level) = project
+            .json_data()
+            .levels
+            .iter()
+            .find(|ldtk_level| ldtk_level.iid == room.iid)
+        else {
+            continue;
+        };
 
-        if let Some(Collision::Inside) = collide(
-            tx,
-            room_aabb.half_extents.truncate() * 2.,
-            player_global_transform.translation(),
-            Vec2::ONE,
-        ) {
-            if room == &player.in_room {
-                continue;
+        let mut chunk = NavChunk::new(RoomLinks {
+            doors: room.rotated_door_connections(*rotation),
+            floor: level.z_index(),
+            stairs_to: room.stairs_to.map(|stairs_to| stairs_to.z_index()),
+        });
+
+        let int_grids = ldtk_level
+            .layer_instances
+            .iter()
+            .flatten()
+            .filter(|layer| !layer.int_grid_csv.is_empty());
+
+        for layer in int_grids {
+            for (index, value) in layer.int_grid_csv.iter().enumerate() {
+                let state = match *value {
+                    v if v == LayerMask::NonWalkable as i32 => WalkableState::NotWalkable,
+                    v if v == LayerMask::RoomBound as i32 => WalkableState::Walkable(DOORWAY_COST),
+                    v if v == LayerMask::Walkable as i32 => WalkableState::Walkable(FLOOR_COST),
+                    v if v == LayerMask::Difficult as i32 => {
+                        WalkableState::Walkable(DIFFICULT_COST)
+                    }
+                    _ => continue,
+                };
+
+                // The IntGrid is stored row by row from the top, tile coordinates start at the bottom
+                let index = index as i32;
+                let tile =
+                    GridCoords::new(index % layer.c_wid, layer.c_hei - 1 - index / layer.c_wid);
+
+                chunk.set(rotation.rotate_tile(tile), Some(state));
             }
+        }
+
+        navmesh_grid.insert_room(room_entity, world_room_coords(room_coords, level), chunk);
+    }
+}
 
-            player.in_room = room.clone();
+pub fn remove_room_navmesh(
+    mut navmesh_grid: ResMut<MeshGrid>,
+    mut removed_rooms: RemovedComponents<Room>,
+) {
+    for room_entity in removed_rooms.read() {
+        navmesh_grid.remove_room(room_entity);
+    }
+}
 
-            let player_pos_in_room = character_transform_to_pos_in_room(
-                player_global_transform,
-                room_transform,
-                room_aabb,
-            );
+pub fn lock_room_placement(
+    mut commands: Commands,
+    placement: Option<Res<RoomPlacement>>,
+    mut room_event: EventReader<RoomBoundsHitEvent>,
+) {
+    let Some(placement) = placement else {
+        return;
+    };
 
-            room_event.send(RoomBoundsHitEvent {
-                character_entity: player_entity,
-                room_entity,
-                room: room.clone(),
-            })
+    for event in &mut room_event.read() {
+        if event.room_entity == placement.room_entity {
+            commands.remove_resource::<RoomPlacement>();
         }
     }
 }
 
-pub fn create_navmesh(mut commands: Commands) {
-    commands.spawn(NavmeshBundle {
-        name: Name::new("Navmesh"),
-        transform: Transform::from_xyz(INT_TILE_SIZE / 2., INT_TILE_SIZE / 2., 1.).into(),
-        ..default()
+pub fn take_stairs(
+    mut player_query: Query<
+        (
+            Entity,
+            &ActionState<CharacterInput>,
+            &mut Transform,
+            &mut Player,
+            Option<&CurrentRoom>,
+        ),
+        With<Player>,
+    >,
+    navmesh_grid: Res<MeshGrid>,
+    mut turn_state: ResMut<TurnState>,
+    mut teleport_event: EventWriter<CharacterTeleportEvent>,
+) {
+    let Ok((player_entity, input, mut transform, mut player, current_room)) =
+        player_query.get_single_mut()
+    else {
+        return;
+    };
+
+    if !input.just_pressed(CharacterInput::TakeStairs) || !turn_state.is_active(player_entity) {
+        return;
+    }
+
+    let Some(stairs_to) = current_room.and_then(|current| current.room.stairs_to) else {
+        return;
+    };
+
+    let current_level = RoomLevel::from_world_position(transform.translation.truncate());
+
+    // Lands where a path up or down the stairs would, so walking and taking them agree
+    let Some(landing) = navmesh_grid.stairs_landing(current_level.z_index(), stairs_to.z_index())
+    else {
+        return;
+    };
+
+    if !turn_state.spend_movement(ROOM_CROSSING_COST, landing) {
+        info!("Not enough movement left to take the stairs");
+        return;
+    }
+
+    info!("Taking the stairs to the {:?} floor", stairs_to);
+
+    transform.translation = tile_to_world(landing).extend(transform.translation.z);
+    player.move_path.clear();
+    player.move_to = None;
+
+    teleport_event.send(CharacterTeleportEvent {
+        character_entity: player_entity,
+        position: transform.translation,
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::room::ROOM_TILE_COUNT;
+
+    /// The walls of a room with a two tile doorway in the middle of its top and left sides
+    fn walls_with_doorways() -> HashSet<GridCoords> {
+        let last = ROOM_TILE_COUNT - 1;
+        let doorway = [ROOM_TILE_COUNT / 2 - 1, ROOM_TILE_COUNT / 2];
+
+        (0..ROOM_TILE_COUNT)
+            .flat_map(|x| (0..ROOM_TILE_COUNT).map(move |y| GridCoords::new(x, y)))
+            .filter(|coords| coords.x == 0 || coords.y == 0 || coords.x == last || coords.y == last)
+            .filter(|coords| {
+                !(coords.y == last && doorway.contains(&coords.x)
+                    || coords.x == 0 && doorway.contains(&coords.y))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn merged_tiles_cover_every_tile_once() {
+        let tiles = walls_with_doorways();
+        let rects = merge_tiles(&tiles);
+
+        let mut covered = Vec::new();
+        for (corner, size) in &rects {
+            for x in 0..size.x {
+                for y in 0..size.y {
+                    covered.push(GridCoords::new(corner.x + x, corner.y + y));
+                }
+            }
+        }
+
+        assert_eq!(covered.len(), tiles.len());
+        assert_eq!(covered.into_iter().collect::<HashSet<_>>(), tiles);
+    }
+
+    #[test]
+    fn walls_merge_into_a_few_colliders() {
+        // Bottom, right, the top on each side of its doorway and the left below and above its
+        // doorway
+        assert_eq!(merge_tiles(&walls_with_doorways()).len(), 6);
+
+        let doorway = [GridCoords::new(0, 5), GridCoords::new(0, 6)];
+        assert_eq!(
+            merge_tiles(&doorway.into_iter().collect()),
+            vec![(GridCoords::new(0, 5), IVec2::new(1, 2))]
+        );
+    }
+}
@@ -0,0 +1,208 @@
+use super::character::{CharacterInput, CharacterProps, CharacterTraits, Dead, Player};
+use super::{world_to_tile, MeshGrid};
+use crate::GameState;
+use bevy::prelude::*;
+use bevy_ecs_ldtk::GridCoords;
+use leafwing_input_manager::prelude::*;
+
+/// How much movement a character gets per point of speed, enough to cross one room
+pub const TILES_PER_SPEED: u32 = 12;
+
+pub struct TurnPlugin;
+
+impl Plugin for TurnPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TurnState>()
+            .add_event::<EndTurnEvent>()
+            .add_systems(
+                Update,
+                (
+                    update_turn_order,
+                    end_turn_on_input,
+                    advance_turn,
+                    begin_turn,
+                    spend_movement,
+                )
+                    .chain()
+                    .run_if(in_state(GameState::Main)),
+            );
+    }
+}
+
+/// Whose turn it is, and how far they can still walk this turn
+#[derive(Resource, Default, Debug)]
+pub struct TurnState {
+    order: Vec<Entity>,
+    index: usize,
+    movement_left: u32,
+    /// Turns ended so far, so a character playing on their own still starts a new turn each time
+    turns_taken: u32,
+    /// The turn and character the current turn has been set up for
+    started_for: Option<(u32, Entity)>,
+    last_tile: Option<GridCoords>,
+}
+
+impl TurnState {
+    pub fn active(&self) -> Option<Entity> {
+        self.order.get(self.index).copied()
+    }
+
+    pub fn is_active(&self, character_entity: Entity) -> bool {
+        self.active() == Some(character_entity)
+    }
+
+    pub fn order(&self) -> &[Entity] {
+        &self.order
+    }
+
+    /// Replace the turn order, keeping the turn with the active character if they are still in it
+    pub fn set_order(&mut self, order: Vec<Entity>) {
+        let active = self.active();
+        self.order = order;
+        self.index = active
+            .and_then(|active| self.order.iter().position(|entity| *entity == active))
+            .unwrap_or(0);
+    }
+
+    /// Hands the turn to a character, for when another peer decides whose turn it is
+    pub fn set_active(&mut self, character_entity: Entity) {
+        if let Some(index) = self
+            .order
+            .iter()
+            .position(|entity| *entity == character_entity)
+        {
+            self.index = index;
+        }
+    }
+
+    /// Movement the active character has left this turn, ordinary floor tiles cost one each
+    pub fn movement_left(&self) -> u32 {
+        self.movement_left
+    }
+
+    /// Spends movement on getting to `tile` some other way than walking, like taking the stairs.
+    /// Nothing is spent and `false` is returned when there isn't enough movement left.
+    pub fn spend_movement(&mut self, cost: u32, tile: GridCoords) -> bool {
+        if cost > self.movement_left {
+            return false;
+        }
+
+        self.movement_left -= cost;
+        // Already paid for, so walking onto it doesn't cost anything more
+        self.last_tile = Some(tile);
+        true
+    }
+}
+
+pub fn movement_budget(props: &CharacterProps) -> u32 {
+    props.speed as u32 * TILES_PER_SPEED
+}
+
+/// Ends the turn of the character, if it is their turn
+#[derive(Event, Debug)]
+pub struct EndTurnEvent {
+    pub character_entity: Entity,
+}
+
+fn update_turn_order(
+    mut turn_state: ResMut<TurnState>,
+    new_characters: Query<Entity, Added<CharacterTraits>>,
+    living_characters: Query<(), (With<CharacterTraits>, Without<Dead>)>,
+) {
+    let mut order = turn_state
+        .order
+        .iter()
+        .copied()
+        .filter(|entity| living_characters.contains(*entity))
+        .collect::<Vec<_>>();
+    order.extend(
+        new_characters
+            .iter()
+            .filter(|entity| !order.contains(entity)),
+    );
+
+    if order != turn_state.order {
+        turn_state.set_order(order);
+    }
+}
+
+fn end_turn_on_input(
+    turn_state: Res<TurnState>,
+    player: Query<(Entity, &ActionState<CharacterInput>), With<Player>>,
+    mut end_turn_event: EventWriter<EndTurnEvent>,
+) {
+    let Ok((player_entity, input)) = player.get_single() else {
+        return;
+    };
+
+    if input.just_pressed(CharacterInput::EndTurn) && turn_state.is_active(player_entity) {
+        end_turn_event.send(EndTurnEvent {
+            character_entity: player_entity,
+        });
+    }
+}
+
+fn advance_turn(mut turn_state: ResMut<TurnState>, mut end_turn_event: EventReader<EndTurnEvent>) {
+    for EndTurnEvent { character_entity } in &mut end_turn_event.read() {
+        if !turn_state.is_active(*character_entity) {
+            continue;
+        }
+
+        turn_state.index = (turn_state.index + 1) % turn_state.order.len();
+        turn_state.turns_taken += 1;
+    }
+}
+
+fn begin_turn(
+    mut turn_state: ResMut<TurnState>,
+    characters: Query<(&CharacterProps, &Transform, Option<&Name>)>,
+) {
+    let active = turn_state.active();
+    let turn = active.map(|active| (turn_state.turns_taken, active));
+    if turn == turn_state.started_for {
+        return;
+    }
+
+    turn_state.started_for = turn;
+    turn_state.movement_left = 0;
+    turn_state.last_tile = None;
+
+    let Some((props, transform, name)) = active.and_then(|active| characters.get(active).ok())
+    else {
+        return;
+    };
+
+    turn_state.movement_left = movement_budget(props);
+    turn_state.last_tile = Some(world_to_tile(transform.translation.truncate()));
+
+    info!(
+        "{}'s turn, {} movement",
+        name.map(|name| name.as_str()).unwrap_or("Unknown"),
+        turn_state.movement_left
+    );
+}
+
+/// Every tile the active character walks onto uses up as much movement as the tile costs
+fn spend_movement(
+    mut turn_state: ResMut<TurnState>,
+    navmesh_grid: Res<MeshGrid>,
+    characters: Query<&Transform>,
+) {
+    let Some(Ok(transform)) = turn_state.active().map(|active| characters.get(active)) else {
+        return;
+    };
+
+    let tile = world_to_tile(transform.translation.truncate());
+    if turn_state.last_tile == Some(tile) {
+        return;
+    }
+
+    if turn_state.last_tile.is_some() {
+        let cost = navmesh_grid
+            .tile(tile)
+            .and_then(|tile| tile.cost())
+            .unwrap_or(1);
+        turn_state.movement_left = turn_state.movement_left.saturating_sub(cost);
+    }
+    turn_state.last_tile = Some(tile);
+}
@@ -5,8 +5,18 @@ use bevy::prelude::*;
 #[allow(dead_code)]
 pub enum GameEvent {
     Move(i32),
-    RoomEnter(Room),
-    RoomLeave(Room),
+    RoomEnter {
+        character_entity: Entity,
+        room_entity: Entity,
+        room: Room,
+    },
+    RoomLeave {
+        character_entity: Entity,
+        room_entity: Entity,
+        room: Room,
+    },
     Damaged(i32),
-    Death,
+    Death {
+        character_entity: Entity,
+    },
 }
@@ -18,6 +18,7 @@ use litcrypt::use_litcrypt;
 mod components;
 mod events;
 mod prelude;
+mod settings;
 mod ui;
 mod utils;
 
@@ -26,6 +27,7 @@ pub enum GameState {
     #[default]
     Loading,
     MainMenu,
+    Lobby,
     InitialSpawn,
     Main,
     Paused,
@@ -35,6 +37,7 @@ use_litcrypt!();
 
 fn main() {
     App::new()
+        .insert_resource(settings::Settings::load())
         .add_state::<GameState>()
         .add_loading_state(
             LoadingState::new(GameState::Loading).continue_to_state(GameState::MainMenu),
@@ -75,6 +78,7 @@ fn main() {
                 .run_if(in_state(GameState::InitialSpawn))
                 .after(components::setup_first_rooms),
         )
+        .add_systems(Startup, settings::warn_settings_file_error)
         .add_systems(OnEnter(GameState::Main), grab_cursor)
         .add_systems(OnExit(GameState::Main), release_cursor)
         .run();
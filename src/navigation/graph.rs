@@ -0,0 +1,137 @@
+use super::{NavGrid, ROOM_CROSSING_COST};
+use bevy::utils::HashSet;
+use bevy_ecs_ldtk::GridCoords;
+
+/// Each door bit, the room it leads to and the door that room needs to have to lead back
+const DOORS: [(u8, GridCoords, u8); 4] = [
+    (0b1000, GridCoords { x: 0, y: 1 }, 0b0010),
+    (0b0100, GridCoords { x: 1, y: 0 }, 0b0001),
+    (0b0010, GridCoords { x: 0, y: -1 }, 0b1000),
+    (0b0001, GridCoords { x: -1, y: 0 }, 0b0100),
+];
+
+impl NavGrid {
+    fn floor_of(&self, room_coords: GridCoords) -> Option<i8> {
+        self.chunk(room_coords).map(|chunk| chunk.links().floor)
+    }
+
+    /// The room taking the stairs from floor `from` to floor `to` ends up in, one whose own stairs
+    /// lead back if there is one
+    pub fn stairs_destination(&self, from: i8, to: i8) -> Option<GridCoords> {
+        self.chunks()
+            .filter(|(_, chunk)| chunk.links().floor == to)
+            .max_by_key(|(room, chunk)| (chunk.links().stairs_to == Some(from), -room.x, -room.y))
+            .map(|(room, _)| room)
+    }
+
+    /// Rooms which can be walked to straight from a room, through matching doors or the stairs
+    pub fn room_neighbours(&self, room_coords: GridCoords) -> Vec<(GridCoords, u32)> {
+        let Some(links) = self.chunk(room_coords).map(|chunk| chunk.links()) else {
+            return Vec::new();
+        };
+
+        let mut neighbours = DOORS
+            .iter()
+            .filter(|(door, _, _)| links.doors & door != 0)
+            .map(|(_, offset, back)| (room_coords + *offset, back))
+            .filter(|(next, back)| {
+                self.chunk(*next)
+                    .is_some_and(|chunk| chunk.links().doors & *back != 0)
+            })
+            .map(|(next, _)| (next, ROOM_CROSSING_COST))
+            .collect::<Vec<_>>();
+
+        if let Some(stairs_to) = links.stairs_to {
+            if let Some(next) = self.stairs_destination(links.floor, stairs_to) {
+                neighbours.push((next, ROOM_CROSSING_COST));
+            }
+        }
+
+        neighbours
+    }
+
+    /// A* over the rooms, from the room `from` is in to the room `to` is in
+    pub fn room_route(&self, from: GridCoords, to: GridCoords) -> Option<Vec<GridCoords>> {
+        use pathfinding::prelude::*;
+
+        let to_floor = self.floor_of(to);
+
+        astar(
+            &from,
+            |&room| self.room_neighbours(room),
+            // Rooms on other floors could be one flight of stairs away, however far they look
+            |&room| {
+                if self.floor_of(room) != to_floor {
+                    return 0;
+                }
+
+                ((room.x - to.x).unsigned_abs() + (room.y - to.y).unsigned_abs())
+                    * ROOM_CROSSING_COST
+            },
+            |&room| room == to,
+        )
+        .map(|(route, _)| route)
+    }
+
+    /// The tile stairs are taken from in a room
+    pub fn stairs_tile(&self, room_coords: GridCoords) -> Option<GridCoords> {
+        let center = self.chunk(room_coords)?.center()?;
+
+        Some(NavGrid::room_origin(room_coords) + center)
+    }
+
+    /// Two level pathfinding. A route from room to room is planned first, then the tile path is
+    /// only searched for inside the rooms along that route.
+    ///
+    /// When the route takes the stairs the path jumps from the stairs tile on one floor to the
+    /// stairs tile on the other.
+    pub fn find_path(&self, from: GridCoords, to: GridCoords) -> Option<Vec<GridCoords>> {
+        self.get(to)?.cost()?;
+
+        let (from_room, _) = NavGrid::split(from);
+        let (to_room, _) = NavGrid::split(to);
+        let route = self.room_route(from_room, to_room)?;
+
+        // Split the route into the parts walked on each floor
+        let mut legs = vec![vec![route[0]]];
+        for pair in route.windows(2) {
+            if self.floor_of(pair[0]) != self.floor_of(pair[1]) {
+                legs.push(Vec::new());
+            }
+            legs.last_mut().unwrap().push(pair[1]);
+        }
+
+        let mut path = Vec::new();
+        let mut start = from;
+        for (index, leg) in legs.iter().enumerate() {
+            let next_leg = legs.get(index + 1);
+            let end = match next_leg {
+                Some(_) => self.stairs_tile(*leg.last()?)?,
+                None => to,
+            };
+
+            let rooms = leg.iter().copied().collect::<HashSet<_>>();
+            let floor = self.floor_of(leg[0]);
+
+            // Doors can line up without the tiles behind them doing so, then any room on the
+            // floor is fair game
+            let leg_path = self
+                .find_tile_path(start, end, |coords| {
+                    rooms.contains(&NavGrid::split(coords).0)
+                })
+                .or_else(|| {
+                    self.find_tile_path(start, end, |coords| {
+                        self.floor_of(NavGrid::split(coords).0) == floor
+                    })
+                })?;
+
+            path.extend(leg_path);
+
+            if let Some(next_leg) = next_leg {
+                start = self.stairs_tile(next_leg[0])?;
+            }
+        }
+
+        Some(path)
+    }
+}
@@ -0,0 +1,233 @@
+use super::ROOM_TILE_COUNT;
+use bevy::prelude::Entity;
+use bevy::utils::HashMap;
+use bevy_ecs_ldtk::GridCoords;
+
+const CHUNK_TILES: usize = (ROOM_TILE_COUNT * ROOM_TILE_COUNT) as usize;
+
+/// Movement used up by walking onto an ordinary floor tile
+pub const FLOOR_COST: u8 = 1;
+/// Squeezing through a doorway takes a bit longer than crossing the floor
+pub const DOORWAY_COST: u8 = 2;
+/// Rubble, furniture and anything else in the way which can still be climbed over
+pub const DIFFICULT_COST: u8 = 3;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum WalkableState {
+    NotWalkable,
+    /// Walking onto the tile uses up this much movement
+    Walkable(u8),
+}
+
+impl WalkableState {
+    pub fn cost(&self) -> Option<u32> {
+        match self {
+            WalkableState::NotWalkable => None,
+            WalkableState::Walkable(cost) => Some(*cost as u32),
+        }
+    }
+}
+
+/// How a room connects to the rooms around it, used to plan routes from room to room
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RoomLinks {
+    /// The room's doors once it has been rotated, where up = 0b1000 and left = 0b0001
+    pub doors: u8,
+    /// Any number identifying the floor the room is on
+    pub floor: i8,
+    /// The floor the stairs in this room lead to
+    pub stairs_to: Option<i8>,
+}
+
+/// The walkable state of every tile of a single room, indexed by the tile's position in the room
+#[derive(Debug, Clone)]
+pub struct NavChunk {
+    tiles: [Option<WalkableState>; CHUNK_TILES],
+    /// Set at runtime, every walkable tile of the room costs at least this much
+    min_cost: u8,
+    links: RoomLinks,
+}
+
+impl Default for NavChunk {
+    fn default() -> Self {
+        Self::new(RoomLinks::default())
+    }
+}
+
+impl NavChunk {
+    pub fn new(links: RoomLinks) -> Self {
+        Self {
+            tiles: [None; CHUNK_TILES],
+            min_cost: 0,
+            links,
+        }
+    }
+
+    pub fn links(&self) -> RoomLinks {
+        self.links
+    }
+
+    /// The walkable tile closest to the middle of the room, where the stairs are taken from
+    pub fn center(&self) -> Option<GridCoords> {
+        let middle = GridCoords::new(ROOM_TILE_COUNT / 2, ROOM_TILE_COUNT / 2);
+
+        self.iter()
+            .filter(|(_, state)| state.cost().is_some())
+            .min_by_key(|(local, _)| (local.x - middle.x).abs() + (local.y - middle.y).abs())
+            .map(|(local, _)| local)
+    }
+    fn index(local: GridCoords) -> Option<usize> {
+        let in_room =
+            (0..ROOM_TILE_COUNT).contains(&local.x) && (0..ROOM_TILE_COUNT).contains(&local.y);
+
+        in_room.then(|| (local.y * ROOM_TILE_COUNT + local.x) as usize)
+    }
+
+    pub fn get(&self, local: GridCoords) -> Option<WalkableState> {
+        Self::index(local).and_then(|index| self.with_min_cost(self.tiles[index]))
+    }
+
+    fn with_min_cost(&self, state: Option<WalkableState>) -> Option<WalkableState> {
+        match state {
+            Some(WalkableState::Walkable(cost)) => {
+                Some(WalkableState::Walkable(cost.max(self.min_cost)))
+            }
+            state => state,
+        }
+    }
+
+    /// Sets a tile of the room, tiles outside of the room are ignored
+    pub fn set(&mut self, local: GridCoords, state: Option<WalkableState>) {
+        if let Some(index) = Self::index(local) {
+            self.tiles[index] = state;
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (GridCoords, WalkableState)> + '_ {
+        self.tiles.iter().enumerate().filter_map(|(index, state)| {
+            let index = index as i32;
+            let local = GridCoords::new(index % ROOM_TILE_COUNT, index / ROOM_TILE_COUNT);
+
+            self.with_min_cost(*state).map(|state| (local, state))
+        })
+    }
+}
+
+/// The navmesh of the whole house, one chunk per placed room keyed by the room's world room coordinates
+#[derive(Debug, Default)]
+pub struct NavGrid {
+    chunks: HashMap<GridCoords, NavChunk>,
+    rooms: HashMap<Entity, GridCoords>,
+    /// Goes up every time a room's tiles change, so paths found on older tiles can be told apart
+    generation: u64,
+    /// Whether paths can step diagonally between tiles
+    diagonals: bool,
+}
+
+impl NavGrid {
+    /// The room a tile is in, and the tile's position in that room
+    pub fn split(coords: GridCoords) -> (GridCoords, GridCoords) {
+        let room = GridCoords::new(
+            coords.x.div_euclid(ROOM_TILE_COUNT),
+            coords.y.div_euclid(ROOM_TILE_COUNT),
+        );
+        let local = GridCoords::new(
+            coords.x.rem_euclid(ROOM_TILE_COUNT),
+            coords.y.rem_euclid(ROOM_TILE_COUNT),
+        );
+
+        (room, local)
+    }
+
+    pub fn get(&self, coords: GridCoords) -> Option<WalkableState> {
+        let (room, local) = Self::split(coords);
+
+        self.chunks.get(&room).and_then(|chunk| chunk.get(local))
+    }
+
+    pub fn chunk(&self, room_coords: GridCoords) -> Option<&NavChunk> {
+        self.chunks.get(&room_coords)
+    }
+
+    pub fn chunks(&self) -> impl Iterator<Item = (GridCoords, &NavChunk)> + '_ {
+        self.chunks.iter().map(|(room, chunk)| (*room, chunk))
+    }
+
+    /// The world tile coordinates of the bottom left tile of a room
+    pub fn room_origin(room_coords: GridCoords) -> GridCoords {
+        GridCoords::new(
+            room_coords.x * ROOM_TILE_COUNT,
+            room_coords.y * ROOM_TILE_COUNT,
+        )
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn diagonals(&self) -> bool {
+        self.diagonals
+    }
+
+    /// Lets paths step diagonally, as long as they don't cut past the corner of an unwalkable tile
+    pub fn set_diagonals(&mut self, diagonals: bool) {
+        if self.diagonals != diagonals {
+            self.diagonals = diagonals;
+            self.generation += 1;
+        }
+    }
+
+    /// Sets the tiles of a room, taking them away from wherever the room was before
+    pub fn insert_chunk(
+        &mut self,
+        room_entity: Entity,
+        room_coords: GridCoords,
+        mut chunk: NavChunk,
+    ) {
+        if let Some(previous) = self.rooms.insert(room_entity, room_coords) {
+            // Slowdowns stay with the room when it is rebuilt somewhere else
+            if let Some(previous) = self.chunks.remove(&previous) {
+                chunk.min_cost = chunk.min_cost.max(previous.min_cost);
+            }
+        }
+        self.rooms
+            .retain(|entity, coords| *entity == room_entity || *coords != room_coords);
+
+        self.chunks.insert(room_coords, chunk);
+        self.generation += 1;
+    }
+
+    /// Makes every walkable tile of the room cost at least `cost`
+    pub fn slow_down_room(&mut self, room_entity: Entity, cost: u8) {
+        let Some(chunk) = self
+            .rooms
+            .get(&room_entity)
+            .and_then(|room_coords| self.chunks.get_mut(room_coords))
+        else {
+            return;
+        };
+
+        chunk.min_cost = chunk.min_cost.max(cost);
+        self.generation += 1;
+    }
+
+    pub fn remove_chunk(&mut self, room_entity: Entity) {
+        let Some(room_coords) = self.rooms.remove(&room_entity) else {
+            return;
+        };
+
+        self.chunks.remove(&room_coords);
+        self.generation += 1;
+    }
+
+    /// Every tile of every room, in world tile coordinates
+    pub fn iter(&self) -> impl Iterator<Item = (GridCoords, WalkableState)> + '_ {
+        self.chunks.iter().flat_map(|(room, chunk)| {
+            let origin = Self::room_origin(*room);
+
+            chunk
+                .iter()
+                .map(move |(local, state)| (local + origin, state))
+        })
+    }
+}
@@ -0,0 +1,18 @@
+//! The navmesh and the pathfinding over it, kept apart from the ECS so it can be benchmarked
+mod graph;
+mod grid;
+mod search;
+
+pub use grid::{
+    NavChunk, NavGrid, RoomLinks, WalkableState, DIFFICULT_COST, DOORWAY_COST, FLOOR_COST,
+};
+pub use search::ReachableTiles;
+
+/// The width and height of a room, in navmesh tiles
+pub const ROOM_TILE_COUNT: i32 = 12;
+
+/// How far apart, in rooms, the grids of two floors are
+pub const FLOOR_ROOM_SPACING: i32 = 64;
+
+/// Movement it takes to get from one room to the next, whether through a door or up the stairs
+pub const ROOM_CROSSING_COST: u32 = ROOM_TILE_COUNT as u32;
@@ -0,0 +1,360 @@
+use super::NavGrid;
+use bevy::prelude::Vec2;
+use bevy::utils::HashMap;
+use bevy_ecs_ldtk::GridCoords;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+impl NavGrid {
+    /// The tiles next to `coord` which can be walked onto, with the movement it takes to step onto them
+    pub fn walkable_neighbours(
+        &self,
+        coord: GridCoords,
+    ) -> impl Iterator<Item = (GridCoords, u32)> + '_ {
+        let up = GridCoords::new(coord.x, coord.y + 1);
+        let right = GridCoords::new(coord.x + 1, coord.y);
+        let down = GridCoords::new(coord.x, coord.y - 1);
+        let left = GridCoords::new(coord.x - 1, coord.y);
+
+        // A diagonal step needs both tiles it passes between to be walkable, so no corner is cut
+        let diagonals = [(up, right), (right, down), (down, left), (left, up)]
+            .into_iter()
+            .filter(|_| self.diagonals())
+            .filter(|(a, b)| self.is_walkable(*a) && self.is_walkable(*b))
+            .map(move |(a, b)| a + b - coord);
+
+        [up, down, left, right]
+            .into_iter()
+            .chain(diagonals)
+            .filter_map(|coord| Some((coord, self.get(coord)?.cost()?)))
+    }
+
+    fn is_walkable(&self, coords: GridCoords) -> bool {
+        self.get(coords).and_then(|tile| tile.cost()).is_some()
+    }
+
+    /// Tile level A*, only walking over tiles for which `allowed` is true
+    pub fn find_tile_path(
+        &self,
+        from: GridCoords,
+        to: GridCoords,
+        allowed: impl Fn(GridCoords) -> bool,
+    ) -> Option<Vec<GridCoords>> {
+        use pathfinding::prelude::*;
+
+        astar(
+            &from,
+            |&coord| {
+                self.walkable_neighbours(coord)
+                    .filter(|(coord, _)| allowed(*coord))
+                    .collect::<Vec<_>>()
+            },
+            // Every step costs at least one, so neither distance ever overestimates
+            |&a| {
+                if self.diagonals() {
+                    (a.x - to.x).unsigned_abs().max((a.y - to.y).unsigned_abs())
+                } else {
+                    (Vec2::new(a.x as f32, a.y as f32) - Vec2::new(to.x as f32, to.y as f32))
+                        .length() as u32
+                }
+            },
+            |&p| p == to,
+        )
+        .map(|(path, _)| path)
+    }
+
+    /// Every tile which can be walked to from `from` with at most `budget` movement, with the
+    /// movement needed to get there
+    pub fn reachable(&self, from: GridCoords, budget: u32) -> ReachableTiles {
+        let mut tiles = HashMap::default();
+        tiles.insert(from, 0);
+
+        // Dijkstra, always expanding the cheapest tile so each one is reached by its cheapest path
+        let mut frontier = BinaryHeap::from([Reverse((0, from.x, from.y))]);
+        while let Some(Reverse((cost, x, y))) = frontier.pop() {
+            let coord = GridCoords::new(x, y);
+            if cost > tiles[&coord] {
+                continue;
+            }
+
+            for (neighbour, step) in self.walkable_neighbours(coord) {
+                let neighbour_cost = cost + step;
+                if neighbour_cost > budget
+                    || tiles
+                        .get(&neighbour)
+                        .is_some_and(|known| *known <= neighbour_cost)
+                {
+                    continue;
+                }
+
+                tiles.insert(neighbour, neighbour_cost);
+                frontier.push(Reverse((neighbour_cost, neighbour.x, neighbour.y)));
+            }
+        }
+
+        ReachableTiles {
+            origin: from,
+            tiles,
+        }
+    }
+
+    /// Shortens a path to where the movement runs out, the first tile is where the walk starts
+    pub fn truncate_path(&self, path: &mut Vec<GridCoords>, budget: u32) {
+        let mut spent = 0;
+        let affordable = path
+            .iter()
+            .skip(1)
+            .take_while(|coords| {
+                // Tiles which are gone from the navmesh can't be walked onto at any cost
+                let Some(cost) = self.get(**coords).and_then(|tile| tile.cost()) else {
+                    return false;
+                };
+
+                spent += cost;
+                spent <= budget
+            })
+            .count();
+
+        path.truncate(affordable + 1);
+    }
+
+    /// String pulls a tile path, keeping only the tiles where it has to turn. Each waypoint can be
+    /// walked to in a straight line from the one before, without going over tiles which cost more
+    /// than the path it replaces.
+    pub fn smooth_path(&self, path: &[GridCoords]) -> Vec<GridCoords> {
+        let Some((&first, rest)) = path.split_first() else {
+            return Vec::new();
+        };
+
+        let mut waypoints = vec![first];
+        let mut anchor = 0;
+        let mut max_cost = 0;
+        for (index, coords) in rest.iter().enumerate() {
+            let index = index + 1;
+            let cost = self.get(*coords).and_then(|tile| tile.cost()).unwrap_or(0);
+
+            if index > anchor + 1 && !self.line_of_sight(path[anchor], *coords, max_cost.max(cost))
+            {
+                anchor = index - 1;
+                max_cost = 0;
+                waypoints.push(path[anchor]);
+            }
+            max_cost = max_cost.max(cost);
+        }
+
+        if rest.last().is_some() {
+            waypoints.extend(path.last());
+        }
+
+        waypoints
+    }
+
+    /// Whether the straight line between two tile centres only crosses walkable tiles costing at
+    /// most `max_cost`. A line going exactly through a corner needs both tiles beside it walkable.
+    pub fn line_of_sight(&self, from: GridCoords, to: GridCoords, max_cost: u32) -> bool {
+        let passable = |coords: GridCoords| {
+            self.get(coords)
+                .and_then(|tile| tile.cost())
+                .is_some_and(|cost| cost <= max_cost)
+        };
+
+        let (dx, dy) = (to.x - from.x, to.y - from.y);
+        let (step_x, step_y) = (dx.signum(), dy.signum());
+        let (nx, ny) = (dx.abs(), dy.abs());
+
+        let mut coords = from;
+        let (mut ix, mut iy) = (0, 0);
+        while ix < nx || iy < ny {
+            // Which tile edge the line crosses next, compared without dividing
+            let next = (1 + 2 * ix) * ny - (1 + 2 * iy) * nx;
+            if next == 0 {
+                if !passable(GridCoords::new(coords.x + step_x, coords.y))
+                    || !passable(GridCoords::new(coords.x, coords.y + step_y))
+                {
+                    return false;
+                }
+                coords.x += step_x;
+                coords.y += step_y;
+                ix += 1;
+                iy += 1;
+            } else if next < 0 {
+                coords.x += step_x;
+                ix += 1;
+            } else {
+                coords.y += step_y;
+                iy += 1;
+            }
+
+            if !passable(coords) {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+/// The answer of [`NavGrid::reachable`]
+#[derive(Debug, Clone)]
+pub struct ReachableTiles {
+    origin: GridCoords,
+    tiles: HashMap<GridCoords, u32>,
+}
+
+impl ReachableTiles {
+    pub fn origin(&self) -> GridCoords {
+        self.origin
+    }
+
+    pub fn contains(&self, coords: GridCoords) -> bool {
+        self.tiles.contains_key(&coords)
+    }
+
+    /// Every reachable tile with the movement it takes to walk there
+    pub fn iter(&self) -> impl Iterator<Item = (GridCoords, u32)> + '_ {
+        self.tiles.iter().map(|(coords, cost)| (*coords, *cost))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::navigation::{
+        NavChunk, RoomLinks, WalkableState, DIFFICULT_COST, FLOOR_COST, ROOM_TILE_COUNT,
+    };
+    use bevy::prelude::Entity;
+
+    /// A single room of floor tiles, apart from the given ones
+    fn room(tiles: &[(i32, i32, WalkableState)]) -> NavGrid {
+        let mut chunk = NavChunk::new(RoomLinks::default());
+        for x in 0..ROOM_TILE_COUNT {
+            for y in 0..ROOM_TILE_COUNT {
+                chunk.set(
+                    GridCoords::new(x, y),
+                    Some(WalkableState::Walkable(FLOOR_COST)),
+                );
+            }
+        }
+        for (x, y, state) in tiles {
+            chunk.set(GridCoords::new(*x, *y), Some(*state));
+        }
+
+        let mut grid = NavGrid::default();
+        grid.set_diagonals(true);
+        grid.insert_chunk(Entity::from_raw(0), GridCoords::new(0, 0), chunk);
+        grid
+    }
+
+    /// The highest cost of any tile the straight line between two waypoints passes over, found
+    /// by stepping along it in small steps, or `None` if it passes over a wall. The step count is
+    /// odd so no step lands exactly on a corner, which a diagonal step only touches.
+    fn highest_cost_along(grid: &NavGrid, from: GridCoords, to: GridCoords) -> Option<u32> {
+        let (from, to) = (
+            Vec2::new(from.x as f32, from.y as f32),
+            Vec2::new(to.x as f32, to.y as f32),
+        );
+
+        (0..=999)
+            .map(|step| from.lerp(to, step as f32 / 999.) + 0.5)
+            .map(|point| GridCoords::new(point.x.floor() as i32, point.y.floor() as i32))
+            .map(|coords| grid.get(coords)?.cost())
+            .try_fold(0, |highest, cost| Some(cost?.max(highest)))
+    }
+
+    #[test]
+    fn line_of_sight_does_not_cut_corners() {
+        let wall = WalkableState::NotWalkable;
+        let grid = room(&[(3, 2, wall)]);
+
+        // Squeezing past the corner of the wall, between it and the floor beside it
+        assert!(!grid.line_of_sight(
+            GridCoords::new(2, 2),
+            GridCoords::new(3, 3),
+            FLOOR_COST as u32
+        ));
+        assert!(!grid.line_of_sight(
+            GridCoords::new(3, 3),
+            GridCoords::new(2, 2),
+            FLOOR_COST as u32
+        ));
+        assert!(!grid.line_of_sight(
+            GridCoords::new(4, 1),
+            GridCoords::new(2, 3),
+            FLOOR_COST as u32
+        ));
+
+        // The other diagonal passes the wall by a whole tile
+        assert!(grid.line_of_sight(
+            GridCoords::new(2, 3),
+            GridCoords::new(3, 4),
+            FLOOR_COST as u32
+        ));
+        assert!(room(&[]).line_of_sight(
+            GridCoords::new(2, 2),
+            GridCoords::new(3, 3),
+            FLOOR_COST as u32
+        ));
+    }
+
+    #[test]
+    fn smoothed_paths_go_around_walls() {
+        let wall = WalkableState::NotWalkable;
+        let grid = room(&(2..10).map(|y| (5, y, wall)).collect::<Vec<_>>());
+
+        let from = GridCoords::new(2, 5);
+        let to = GridCoords::new(8, 5);
+        let path = grid.find_path(from, to).unwrap();
+        let smoothed = grid.smooth_path(&path);
+
+        assert!(smoothed.len() > 2);
+        assert_eq!(smoothed.first(), Some(&from));
+        assert_eq!(smoothed.last(), Some(&to));
+        for leg in smoothed.windows(2) {
+            assert!(
+                highest_cost_along(&grid, leg[0], leg[1]).is_some(),
+                "{:?} to {:?} crosses the wall",
+                leg[0],
+                leg[1]
+            );
+        }
+    }
+
+    #[test]
+    fn smoothing_does_not_cross_higher_cost_tiles() {
+        let difficult = WalkableState::Walkable(DIFFICULT_COST);
+        let grid = room(
+            &(3..8)
+                .flat_map(|x| (1..4).map(move |y| (x, y, difficult)))
+                .collect::<Vec<_>>(),
+        );
+
+        let from = GridCoords::new(1, 2);
+        let to = GridCoords::new(9, 2);
+        let path = grid.find_path(from, to).unwrap();
+        assert!(path
+            .iter()
+            .all(|coords| grid.get(*coords) == Some(WalkableState::Walkable(FLOOR_COST))));
+
+        let smoothed = grid.smooth_path(&path);
+        assert!(smoothed.len() > 2);
+        for leg in smoothed.windows(2) {
+            assert_eq!(
+                highest_cost_along(&grid, leg[0], leg[1]),
+                Some(FLOOR_COST as u32),
+                "{:?} to {:?} cuts through the difficult tiles",
+                leg[0],
+                leg[1]
+            );
+        }
+    }
+
+    #[test]
+    fn smoothing_straightens_open_floor() {
+        let grid = room(&[]);
+        let from = GridCoords::new(1, 1);
+        let to = GridCoords::new(9, 4);
+
+        let path = grid.find_path(from, to).unwrap();
+        assert_eq!(grid.smooth_path(&path), vec![from, to]);
+    }
+}
@@ -0,0 +1,116 @@
+use bevy::prelude::*;
+use lazy_static::lazy_static;
+use litcrypt::lc;
+use ron::extensions::Extensions;
+use serde::Deserialize;
+use std::path::Path;
+
+lazy_static! {
+    static ref DEFAULT_SERVER_URL: String = lc!("ws://devinserver.biddydev.com:3536");
+}
+
+/// Room everyone ends up in when no other room is picked
+const DEFAULT_ROOM: &str = "haunted_mansion";
+
+/// File read from the working directory for anything not given on the command line or environment
+const SETTINGS_FILE: &str = "settings.ron";
+
+const SERVER_URL_FLAG: &str = "--server";
+const ROOM_FLAG: &str = "--room";
+
+const SERVER_URL_ENV: &str = "HAUNTED_MANSION_SERVER";
+const ROOM_ENV: &str = "HAUNTED_MANSION_ROOM";
+
+/// Where to find other players. Every field is taken from the first of the command line, the
+/// environment, the settings file and the built in default which has it.
+#[derive(Resource, Debug, Clone)]
+pub struct Settings {
+    /// The matchbox signaling server, without a room
+    pub server_url: String,
+    /// Only players in the same room are matched together, so groups can play apart
+    pub room: String,
+    /// Why the settings file was ignored, kept until logging is up
+    pub file_error: Option<String>,
+}
+
+/// What a settings file may hold, anything left out falls back to the default.
+///
+/// ```ron
+/// (server_url: "ws://localhost:3536", room: "friday_night")
+/// ```
+#[derive(Deserialize, Default, Debug)]
+#[serde(default)]
+struct SettingsFile {
+    server_url: Option<String>,
+    room: Option<String>,
+}
+
+impl Settings {
+    pub fn load() -> Self {
+        let args = std::env::args().collect::<Vec<_>>();
+        let (file, file_error) = match SettingsFile::load(Path::new(SETTINGS_FILE)) {
+            Ok(file) => (file, None),
+            Err(err) => (SettingsFile::default(), Some(err)),
+        };
+
+        Self {
+            server_url: flag(&args, SERVER_URL_FLAG)
+                .or_else(|| env(SERVER_URL_ENV))
+                .or(file.server_url)
+                .unwrap_or_else(|| DEFAULT_SERVER_URL.clone()),
+            room: flag(&args, ROOM_FLAG)
+                .or_else(|| env(ROOM_ENV))
+                .or(file.room)
+                .unwrap_or_else(|| DEFAULT_ROOM.to_string()),
+            file_error,
+        }
+    }
+
+    /// The address of the room on the signaling server
+    pub fn room_url(&self) -> String {
+        format!("{}/{}", self.server_url.trim_end_matches('/'), self.room)
+    }
+
+    /// The address of the room on a signaling server hosted by this game
+    pub fn hosted_room_url(&self, port: u16) -> String {
+        format!("ws://127.0.0.1:{}/{}", port, self.room)
+    }
+}
+
+impl SettingsFile {
+    /// A missing file is the same as an empty one, only a file which can't be read is an error
+    fn load(path: &Path) -> Result<Self, String> {
+        let Ok(contents) = std::fs::read_to_string(path) else {
+            return Ok(Self::default());
+        };
+
+        ron::Options::default()
+            .with_default_extension(Extensions::IMPLICIT_SOME)
+            .from_str(&contents)
+            .map_err(|err| format!("Ignoring {}: {}", path.display(), err))
+    }
+}
+
+/// Settings are loaded while the app is being built, before there is any logging to report to
+pub fn warn_settings_file_error(settings: Res<Settings>) {
+    if let Some(err) = &settings.file_error {
+        warn!("{}", err);
+    }
+}
+
+/// The value of `--name value` or `--name=value`, if it was passed
+fn flag(args: &[String], name: &str) -> Option<String> {
+    args.iter().enumerate().find_map(|(index, arg)| {
+        if arg == name {
+            return args.get(index + 1).cloned();
+        }
+
+        arg.strip_prefix(name)?
+            .strip_prefix('=')
+            .map(str::to_string)
+    })
+}
+
+fn env(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|value| !value.is_empty())
+}
@@ -0,0 +1,32 @@
+//! A matchbox signaling server which can run next to the game, so a LAN or a single machine can
+//! play without the remote server.
+//!
+//! Every peer connecting to it is matched with every other one, the room in the url is not used
+//! to keep groups apart the way the remote server does.
+use bevy::prelude::*;
+use bevy_matchbox::{matchbox_signaling::SignalingServer, prelude::*};
+use std::net::{Ipv4Addr, SocketAddr};
+
+/// Port the bundled server listens on when none is given
+pub const SIGNALING_PORT: u16 = 3536;
+
+/// Starts a full mesh signaling server listening on every interface on `port`. It keeps running
+/// for as long as the returned resource is in the world.
+pub fn start_signaling_server(port: u16) -> MatchboxServer {
+    let address = SocketAddr::from((Ipv4Addr::UNSPECIFIED, port));
+    info!("Starting signaling server on {}", address);
+
+    MatchboxServer::from(
+        SignalingServer::full_mesh_builder(address)
+            .on_connection_request(|connection| {
+                info!("Connection request: {:?}", connection);
+                Ok(true)
+            })
+            .on_id_assignment(|(socket, id)| info!("{} is peer {:?}", socket, id))
+            .on_peer_connected(|id| info!("Peer {:?} joined", id))
+            .on_peer_disconnected(|id| info!("Peer {:?} left", id))
+            .cors()
+            .trace()
+            .build(),
+    )
+}
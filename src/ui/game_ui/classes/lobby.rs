@@ -0,0 +1,38 @@
+use bevy::prelude::*;
+
+pub fn c_character_row(b: &mut NodeBundle) {
+    b.style = Style {
+        display: Display::Flex,
+        flex_direction: FlexDirection::Row,
+        margin: UiRect::bottom(Val::Px(10.)),
+        ..default()
+    };
+}
+
+pub fn c_character_card(_: &AssetServer, b: &mut ButtonBundle) {
+    b.background_color = BackgroundColor(Color::NONE);
+    b.border_color = BorderColor(Color::WHITE);
+    b.style = Style {
+        display: Display::Flex,
+        flex_direction: FlexDirection::Column,
+        align_items: AlignItems::Center,
+        border: UiRect::all(Val::Px(2.)),
+        padding: UiRect::all(Val::Px(8.)),
+        margin: UiRect::horizontal(Val::Px(6.)),
+        ..default()
+    };
+}
+
+pub fn c_headshot(b: &mut NodeBundle) {
+    b.style = Style {
+        width: Val::Px(96.),
+        height: Val::Px(96.),
+        margin: UiRect::bottom(Val::Px(6.)),
+        ..default()
+    };
+}
+
+pub fn c_card_text(assets: &AssetServer, b: &mut TextStyle) {
+    super::main::c_button_text(assets, b);
+    b.font_size = 16.;
+}
@@ -33,6 +33,23 @@ pub fn c_inventory_box(b: &mut NodeBundle) {
     };
 }
 
+pub fn c_inventory_tooltip(b: &mut TextBundle) {
+    b.background_color = BackgroundColor(Color::rgba(0., 0., 0., 0.95));
+    b.style = Style {
+        display: Display::None,
+        position_type: PositionType::Absolute,
+        bottom: Val::Px(80.),
+        max_width: Val::Px(320.),
+        padding: UiRect::all(Val::Px(8.)),
+        ..default()
+    };
+}
+
+pub fn c_inventory_tooltip_text(assets: &AssetServer, b: &mut TextStyle) {
+    c_button_text(assets, b);
+    b.font_size = 16.;
+}
+
 pub fn c_inventory_container(b: &mut NodeBundle) {
     b.background_color = BackgroundColor(Color::rgba(0., 0., 0., 0.95));
     b.style = Style {
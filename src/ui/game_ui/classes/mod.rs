@@ -1,2 +1,3 @@
+pub mod lobby;
 pub mod main;
 pub mod pause;
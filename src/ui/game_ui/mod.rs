@@ -1,12 +1,14 @@
-use crate::components::{LobbyConfig, StartMultiplayer};
-use crate::{ui::OccludeUI, GameState};
+use crate::components::{
+    CardAssets, CardDefinitions, CharacterType, Headshots, Inventory, LobbyAction, LobbyConfig,
+    LobbyPeers, Player, StartMultiplayer, UseCardEvent, INVENTORY_CAPACITY, MAX_PLAYERS,
+    PLAYER_COUNT_PICKABLE,
+};
+use crate::{settings::Settings, ui::OccludeUI, GameState};
 use bevy::{app::AppExit, prelude::*};
 use bevy_ui_dsl::*;
 
 pub mod classes;
 
-const PLAYER_INVENTORY_COUNT: u8 = 6;
-
 #[derive(Component)]
 struct TextInput;
 
@@ -21,9 +23,30 @@ struct PauseUiParent;
 #[derive(Component, Reflect)]
 struct MainMenuUiParent;
 
+#[derive(Component, Reflect)]
+struct LobbyUiParent;
+
+/// Text listing everyone in the lobby and whether they are ready
+#[derive(Component)]
+struct LobbyPeerList;
+
+#[derive(Component)]
+struct LobbyPlayerCount;
+
+/// Text on a character's card saying who has picked them
+#[derive(Component)]
+struct CharacterCardStatus(CharacterType);
+
 #[derive(Component)]
 struct AnimateTransition;
 
+/// One of the boxes of the inventory bar, showing the card in that slot of the player's inventory
+#[derive(Component)]
+struct InventorySlot(usize);
+
+#[derive(Component)]
+struct InventoryTooltip;
+
 impl Plugin for GameUiPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Update, animate_button_interactions)
@@ -33,6 +56,12 @@ impl Plugin for GameUiPlugin {
             .add_systems(OnExit(GameState::Paused), destroy_pause_ui_layout)
             .add_systems(OnEnter(GameState::MainMenu), build_main_menu_ui)
             .add_systems(OnExit(GameState::MainMenu), destroy_main_menu_ui)
+            .add_systems(OnEnter(GameState::Lobby), build_lobby_ui)
+            .add_systems(OnExit(GameState::Lobby), destroy_lobby_ui)
+            .add_systems(
+                Update,
+                (handle_lobby_buttons, update_lobby_ui).run_if(in_state(GameState::Lobby)),
+            )
             .add_systems(
                 Update,
                 pause_button_system.run_if(in_state(GameState::Paused)),
@@ -40,6 +69,15 @@ impl Plugin for GameUiPlugin {
             .add_systems(
                 Update,
                 handle_main_menu_buttons.run_if(in_state(GameState::MainMenu)),
+            )
+            .add_systems(
+                Update,
+                (
+                    update_inventory_slots,
+                    show_inventory_tooltip,
+                    use_inventory_slot,
+                )
+                    .run_if(in_state(GameState::Main)),
             );
     }
 }
@@ -59,6 +97,8 @@ fn build_main_ui_layout(
     use classes::main::*;
 
     let mut inventory_bounding_box = None;
+    let mut inventory_slots = Vec::with_capacity(INVENTORY_CAPACITY);
+    let mut tooltip = None;
 
     let root_entity = root(c_root, &asset_server, &mut commands, |p| {
         node(c_character_list, p, |p| {
@@ -68,13 +108,23 @@ fn build_main_ui_layout(
         });
 
         node(c_inventory_container, p, |p| {
-            for _ in 0..PLAYER_INVENTORY_COUNT {
-                node(c_inventory_box, p, |_| {});
+            for _ in 0..INVENTORY_CAPACITY {
+                inventory_slots.push(node(c_inventory_box, p, |_| {}));
             }
         })
         .set(&mut inventory_bounding_box);
+
+        text("", c_inventory_tooltip, c_inventory_tooltip_text, p).set(&mut tooltip);
     });
 
+    for (slot, slot_entity) in inventory_slots.into_iter().enumerate() {
+        commands
+            .entity(slot_entity)
+            .insert((InventorySlot(slot), Interaction::default()));
+    }
+
+    commands.entity(tooltip.unwrap()).insert(InventoryTooltip);
+
     commands
         .entity(root_entity)
         .insert((GameUiParent, Name::new("Main UI Layout")));
@@ -84,6 +134,114 @@ fn build_main_ui_layout(
         .insert(OccludeUI);
 }
 
+fn update_inventory_slots(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    card_assets: Res<CardAssets>,
+    definitions: Res<Assets<CardDefinitions>>,
+    player: Query<Ref<Inventory>, With<Player>>,
+    slots: Query<(Entity, Ref<InventorySlot>)>,
+) {
+    use classes::main::c_inventory_tooltip_text;
+
+    let Ok(inventory) = player.get_single() else {
+        return;
+    };
+    let Some(definitions) = definitions.get(&card_assets.definitions) else {
+        return;
+    };
+
+    // The slots are rebuilt with the main UI, so they need filling in even if nothing changed
+    if !inventory.is_changed() && !slots.iter().any(|(_, slot)| slot.is_added()) {
+        return;
+    }
+
+    for (slot_entity, slot) in &slots {
+        commands.entity(slot_entity).despawn_descendants();
+
+        let Some(definition) = inventory.get(slot.0).and_then(|card| definitions.get(card)) else {
+            continue;
+        };
+
+        commands.entity(slot_entity).with_children(|p| {
+            if let Some(art) = &definition.art {
+                p.spawn(ImageBundle {
+                    image: UiImage::new(asset_server.load(art)),
+                    style: Style {
+                        width: Val::Percent(100.),
+                        height: Val::Percent(100.),
+                        ..default()
+                    },
+                    ..default()
+                });
+                return;
+            }
+
+            // No art yet, show the initials of the card instead
+            let initials = definition
+                .name
+                .split_whitespace()
+                .filter_map(|word| word.chars().next())
+                .collect::<String>();
+
+            let mut style = TextStyle::default();
+            c_inventory_tooltip_text(&asset_server, &mut style);
+            p.spawn(TextBundle::from_section(initials, style));
+        });
+    }
+}
+
+fn show_inventory_tooltip(
+    card_assets: Res<CardAssets>,
+    definitions: Res<Assets<CardDefinitions>>,
+    player: Query<&Inventory, With<Player>>,
+    slots: Query<(&Interaction, &InventorySlot)>,
+    mut tooltip: Query<(&mut Text, &mut Style), With<InventoryTooltip>>,
+) {
+    let Ok((mut text, mut style)) = tooltip.get_single_mut() else {
+        return;
+    };
+
+    let hovered = slots
+        .iter()
+        .find(|(interaction, _)| **interaction != Interaction::None)
+        .and_then(|(_, slot)| player.get_single().ok()?.get(slot.0))
+        .and_then(|card| definitions.get(&card_assets.definitions)?.get(card));
+
+    let Some(definition) = hovered else {
+        style.display = Display::None;
+        return;
+    };
+
+    style.display = Display::Flex;
+    text.sections[0].value = if definition.flavour.is_empty() {
+        definition.name.clone()
+    } else {
+        format!("{}\n{}", definition.name, definition.flavour)
+    };
+}
+
+fn use_inventory_slot(
+    slots: Query<(&Interaction, &InventorySlot), Changed<Interaction>>,
+    player: Query<Entity, With<Player>>,
+    mut use_card_event: EventWriter<UseCardEvent>,
+) {
+    let Ok(player_entity) = player.get_single() else {
+        return;
+    };
+
+    for (interaction, slot) in &slots {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        use_card_event.send(UseCardEvent {
+            character_entity: player_entity,
+            slot: slot.0,
+        });
+    }
+}
+
 mod pause_components {
     use bevy::prelude::*;
 
@@ -174,6 +332,7 @@ mod main_menu_components {
     pub enum ButtonType {
         Singleplayer,
         Multiplayer,
+        HostMultiplayer,
         Quit,
     }
 }
@@ -185,6 +344,7 @@ fn build_main_menu_ui(mut commands: Commands, asset_server: Res<AssetServer>) {
 
     let mut singleplayer = None;
     let mut multiplayer = None;
+    let mut host_multiplayer = None;
     let mut quit = None;
 
     let main_menu_entity = root(c_root, &asset_server, &mut commands, |p| {
@@ -193,6 +353,8 @@ fn build_main_menu_ui(mut commands: Commands, asset_server: Res<AssetServer>) {
                 .set(&mut singleplayer);
             text_button("Start Multiplayer", c_button_with_text, c_pause_text, p)
                 .set(&mut multiplayer);
+            text_button("Host Game", c_button_with_text, c_pause_text, p)
+                .set(&mut host_multiplayer);
             text_button("Quit", c_button_with_text, c_pause_text, p).set(&mut quit);
         });
     });
@@ -203,6 +365,9 @@ fn build_main_menu_ui(mut commands: Commands, asset_server: Res<AssetServer>) {
     commands
         .entity(multiplayer.unwrap())
         .insert((ButtonType::Multiplayer, AnimateTransition));
+    commands
+        .entity(host_multiplayer.unwrap())
+        .insert((ButtonType::HostMultiplayer, AnimateTransition));
     commands
         .entity(quit.unwrap())
         .insert((ButtonType::Quit, AnimateTransition));
@@ -242,8 +407,12 @@ fn handle_main_menu_buttons(
                 game_state.set(GameState::InitialSpawn);
             }
             main_menu_components::ButtonType::Multiplayer => {
-                game_state.set(GameState::InitialSpawn);
-                start_multiplayer.send(StartMultiplayer);
+                game_state.set(GameState::Lobby);
+                start_multiplayer.send(StartMultiplayer { host: false });
+            }
+            main_menu_components::ButtonType::HostMultiplayer => {
+                game_state.set(GameState::Lobby);
+                start_multiplayer.send(StartMultiplayer { host: true });
             }
             main_menu_components::ButtonType::Quit => {
                 app_exit.send(AppExit);
@@ -251,3 +420,228 @@ fn handle_main_menu_buttons(
         }
     }
 }
+
+mod lobby_components {
+    use bevy::prelude::*;
+
+    #[derive(Component)]
+    pub enum ButtonType {
+        Ready,
+        FewerPlayers,
+        MorePlayers,
+        ForceStart,
+        PickCharacter(super::CharacterType),
+    }
+}
+
+fn build_lobby_ui(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<Settings>,
+    headshots: Res<Headshots>,
+) {
+    use classes::lobby::*;
+    use classes::main::{c_button_text, c_button_with_text, c_root};
+    use classes::pause::{c_center, c_pause_text, pad_below};
+    use lobby_components::*;
+
+    let mut peer_list = None;
+    let mut player_count = None;
+    let mut buttons = Vec::new();
+    let mut cards = Vec::new();
+
+    let lobby_entity = root(c_root, &asset_server, &mut commands, |p| {
+        node(c_center, p, |p| {
+            text(format!("Room {}", settings.room), (), c_pause_text, p);
+            node(pad_below, p, |_| {});
+            node(c_character_row, p, |p| {
+                for character in CharacterType::ALL {
+                    let traits = character.traits();
+                    let mut headshot = None;
+                    let mut status = None;
+
+                    let card = button(c_character_card, p, |p| {
+                        node(c_headshot, p, |_| {}).set(&mut headshot);
+                        text(character.name(), (), c_card_text, p);
+                        text(
+                            format!(
+                                "Speed {} Might {}\nSanity {} Knowledge {}",
+                                traits.speed.value(),
+                                traits.might.value(),
+                                traits.sanity.value(),
+                                traits.knowledge.value()
+                            ),
+                            (),
+                            c_card_text,
+                            p,
+                        );
+                        text("", (), c_card_text, p).set(&mut status);
+                    });
+
+                    cards.push((character, card, headshot.unwrap(), status.unwrap()));
+                }
+            });
+            text("", (), c_button_text, p).set(&mut peer_list);
+            node(pad_below, p, |_| {});
+            text("", (), c_button_text, p).set(&mut player_count);
+
+            let mut button_types = vec![
+                ("Ready", ButtonType::Ready),
+                ("Force Start", ButtonType::ForceStart),
+            ];
+            if PLAYER_COUNT_PICKABLE {
+                button_types.splice(
+                    0..0,
+                    [
+                        ("Fewer Players", ButtonType::FewerPlayers),
+                        ("More Players", ButtonType::MorePlayers),
+                    ],
+                );
+            } else {
+                text(
+                    format!(
+                        "There are only {} characters, so every game is for {} players",
+                        MAX_PLAYERS, MAX_PLAYERS
+                    ),
+                    (),
+                    c_button_text,
+                    p,
+                );
+            }
+
+            for (label, button_type) in button_types {
+                let button = text_button(label, c_button_with_text, c_button_text, p);
+                buttons.push((button, button_type));
+            }
+        });
+    });
+
+    commands.entity(peer_list.unwrap()).insert(LobbyPeerList);
+    commands
+        .entity(player_count.unwrap())
+        .insert(LobbyPlayerCount);
+
+    for (button, button_type) in buttons {
+        commands
+            .entity(button)
+            .insert((button_type, AnimateTransition));
+    }
+
+    for (character, card, headshot, status) in cards {
+        commands
+            .entity(card)
+            .insert((ButtonType::PickCharacter(character), AnimateTransition));
+        commands.entity(headshot).with_children(|p| {
+            p.spawn(ImageBundle {
+                image: UiImage::new(headshots.headshot(character)),
+                style: Style {
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    ..default()
+                },
+                ..default()
+            });
+        });
+        commands
+            .entity(status)
+            .insert(CharacterCardStatus(character));
+    }
+
+    commands
+        .entity(lobby_entity)
+        .insert((Name::new("Lobby UI Layout"), LobbyUiParent));
+}
+
+fn destroy_lobby_ui(mut commands: Commands, lobby_ui: Query<Entity, With<LobbyUiParent>>) {
+    let Ok(entity) = lobby_ui.get_single() else {
+        return;
+    };
+
+    commands.entity(entity).despawn_recursive();
+}
+
+fn update_lobby_ui(
+    lobby_peers: Res<LobbyPeers>,
+    lobby_config: Res<LobbyConfig>,
+    mut peer_list: Query<&mut Text, (With<LobbyPeerList>, Without<LobbyPlayerCount>)>,
+    mut player_count: Query<&mut Text, (With<LobbyPlayerCount>, Without<LobbyPeerList>)>,
+    mut card_statuses: Query<
+        (&mut Text, &CharacterCardStatus),
+        (Without<LobbyPeerList>, Without<LobbyPlayerCount>),
+    >,
+) {
+    if !lobby_peers.is_changed() && !lobby_config.is_changed() {
+        return;
+    }
+
+    if let Ok(mut peer_list) = peer_list.get_single_mut() {
+        peer_list.sections[0].value = if lobby_peers.local().is_none() {
+            "Connecting...".to_string()
+        } else {
+            lobby_peers
+                .all()
+                .map(|peer| {
+                    let name = if Some(peer) == lobby_peers.local() {
+                        "You".to_string()
+                    } else {
+                        // The start of the id is enough to tell players apart
+                        peer.0.to_string()[..8].to_string()
+                    };
+                    let host = if Some(peer) == lobby_peers.host() {
+                        " (host)"
+                    } else {
+                        ""
+                    };
+                    let ready = if lobby_peers.is_ready(peer) {
+                        "ready"
+                    } else {
+                        "not ready"
+                    };
+                    let character = lobby_peers
+                        .character_of(peer)
+                        .map_or("no character", |character| character.name());
+
+                    format!("{}{} - {} - {}", name, host, character, ready)
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+    }
+
+    if let Ok(mut player_count) = player_count.get_single_mut() {
+        player_count.sections[0].value = format!(
+            "Waiting for {} of {} players",
+            lobby_peers.all().count(),
+            (lobby_config.requested_players + 1).min(MAX_PLAYERS)
+        );
+    }
+
+    for (mut text, status) in &mut card_statuses {
+        text.sections[0].value = match lobby_peers.owner(status.0) {
+            None => "Free".to_string(),
+            Some(owner) if Some(owner) == lobby_peers.local() => "Yours".to_string(),
+            Some(_) => "Taken".to_string(),
+        };
+    }
+}
+
+fn handle_lobby_buttons(
+    lobby_buttons: Query<(&Interaction, &lobby_components::ButtonType), Changed<Interaction>>,
+    mut lobby_action: EventWriter<LobbyAction>,
+) {
+    for (interaction, button_type) in &lobby_buttons {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        lobby_action.send(match *button_type {
+            lobby_components::ButtonType::Ready => LobbyAction::ToggleReady,
+            lobby_components::ButtonType::FewerPlayers => LobbyAction::ChangePlayerCount(-1),
+            lobby_components::ButtonType::MorePlayers => LobbyAction::ChangePlayerCount(1),
+            lobby_components::ButtonType::ForceStart => LobbyAction::ForceStart,
+            lobby_components::ButtonType::PickCharacter(character) => {
+                LobbyAction::PickCharacter(character)
+            }
+        });
+    }
+}
@@ -0,0 +1,107 @@
+//! Two clients finding each other through the bundled signaling server, the same way a game
+//! hosting and a game joining on one machine do before `init_networked_players` runs.
+use bevy::tasks::{IoTaskPool, TaskPool};
+use bevy::utils::HashSet;
+use bevy_matchbox::prelude::*;
+use std::net::TcpListener;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+use the_haunted_mansion::signaling::start_signaling_server;
+
+/// How long the clients get to connect and talk before the test gives up
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A port nobody is listening on. It is released before the server binds it, which only matters
+/// if something else grabs it in between.
+fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .map(|address| address.port())
+        .expect("no free port to run the server on")
+}
+
+/// Keeps track of who a socket is connected to, as the lobby does
+fn update_peers(socket: &mut MatchboxSocket<SingleChannel>, peers: &mut HashSet<PeerId>) {
+    for (peer, state) in socket.update_peers() {
+        match state {
+            PeerState::Connected => {
+                peers.insert(peer);
+            }
+            PeerState::Disconnected => {
+                peers.remove(&peer);
+            }
+        }
+    }
+}
+
+#[test]
+fn two_clients_connect_through_the_bundled_server() {
+    // The game gets the pool from its plugins, the server and sockets run their loops on it
+    IoTaskPool::get_or_init(TaskPool::new);
+
+    let port = free_port();
+    let _server = start_signaling_server(port);
+    let room_url = format!("ws://127.0.0.1:{}/haunted_mansion", port);
+
+    let mut host = MatchboxSocket::new_reliable(room_url.clone());
+    let mut guest = MatchboxSocket::new_reliable(room_url);
+    let mut host_peers = HashSet::new();
+    let mut guest_peers = HashSet::new();
+
+    let started = Instant::now();
+    let (host_id, guest_id) = loop {
+        update_peers(&mut host, &mut host_peers);
+        update_peers(&mut guest, &mut guest_peers);
+
+        if let (Some(host_id), Some(guest_id)) = (host.id(), guest.id()) {
+            if host_peers.contains(&guest_id) && guest_peers.contains(&host_id) {
+                break (host_id, guest_id);
+            }
+        }
+
+        assert!(
+            started.elapsed() < TIMEOUT,
+            "the clients never saw each other"
+        );
+        sleep(Duration::from_millis(50));
+    };
+
+    assert_eq!(host_peers.len(), 1);
+    assert_eq!(guest_peers.len(), 1);
+
+    // The first thing a game sends is its hello, so the channel has to carry messages both ways
+    host.send(b"hello from the host".to_vec().into_boxed_slice(), guest_id);
+    guest.send(b"hello from the guest".to_vec().into_boxed_slice(), host_id);
+
+    let mut host_heard = None;
+    let mut guest_heard = None;
+    while host_heard.is_none() || guest_heard.is_none() {
+        update_peers(&mut host, &mut host_peers);
+        update_peers(&mut guest, &mut guest_peers);
+
+        for (peer, data) in host.receive() {
+            host_heard = Some((peer, data));
+        }
+        for (peer, data) in guest.receive() {
+            guest_heard = Some((peer, data));
+        }
+
+        assert!(
+            started.elapsed() < TIMEOUT,
+            "the clients never heard each other"
+        );
+        sleep(Duration::from_millis(50));
+    }
+
+    assert_eq!(
+        host_heard,
+        Some((
+            guest_id,
+            b"hello from the guest".to_vec().into_boxed_slice()
+        ))
+    );
+    assert_eq!(
+        guest_heard,
+        Some((host_id, b"hello from the host".to_vec().into_boxed_slice()))
+    );
+}